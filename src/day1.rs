@@ -1,81 +1,149 @@
 const DATA: &str = include_str!("day1part1.txt");
+use std::collections::{HashMap, VecDeque};
 
-pub fn part1() {
-    let mut result = 0;
-    for line in DATA.lines() {
-        let nums: Vec<_> = line.chars().flat_map(|x| x.to_digit(10)).collect();
-        let a = nums[0];
-        let b = nums[nums.len() - 1];
-        result += 10 * a + b;
-    }
-    println!("result: {result}");
+const DIGITS: [(&str, u32); 10] = [
+    ("0", 0),
+    ("1", 1),
+    ("2", 2),
+    ("3", 3),
+    ("4", 4),
+    ("5", 5),
+    ("6", 6),
+    ("7", 7),
+    ("8", 8),
+    ("9", 9),
+];
+
+const ENGLISH: [(&str, u32); 9] = [
+    ("one", 1),
+    ("two", 2),
+    ("three", 3),
+    ("four", 4),
+    ("five", 5),
+    ("six", 6),
+    ("seven", 7),
+    ("eight", 8),
+    ("nine", 9),
+];
+
+pub fn part1() -> Option<()> {
+    calibrate(&[])
 }
 
-pub fn part2() {
-    let mut result = 0;
-    for line in DATA.lines() {
-        let cs = line.chars().collect();
-        let a = find_first(&cs);
-        let b = find_last(&cs);
-        result += 10 * a + b;
-    }
+pub fn part2() -> Option<()> {
+    calibrate(&ENGLISH)
+}
+
+/// Sums the calibration values with `words` recognised alongside the
+/// plain digits.
+pub fn calibrate(words: &[(&str, u32)]) -> Option<()> {
+    let result = calibration_total(DATA, words)
+        .map_err(|e| eprintln!("{e}"))
+        .ok()?;
     println!("result: {result}");
+    Some(())
 }
 
-fn find_first(v: &Vec<char>) -> i32 {
-    for i in 0..v.len() {
-        if v[i].is_digit(10) {
-            return v[i].to_digit(10).unwrap() as i32;
-        }
-        if let Some((c, _)) = word_number_at_index(v, i) {
-            return c.to_digit(10).unwrap() as i32;
-        }
+fn calibration_total(input: &str, words: &[(&str, u32)]) -> Result<u64, String> {
+    let vocab = DIGITS.iter().chain(words).copied().collect::<Vec<_>>();
+    let scanner = Scanner::new(&vocab)?;
+    let mut total = 0;
+    for (i, line) in input.lines().enumerate() {
+        let (a, b) = scanner
+            .first_last(line)
+            .ok_or_else(|| format!("no digit on line {}: {line:?}", i + 1))?;
+        total += 10 * a as u64 + b as u64;
     }
-    panic!("no numbers");
+    Ok(total)
+}
+
+/// Aho–Corasick automaton over a set of words, each standing for a value.
+/// One pass over a line finds every occurrence of every word, including
+/// ones that overlap like the "one" inside "twone".
+struct Scanner {
+    goto: Vec<HashMap<char, usize>>,
+    /// The state for the longest proper suffix that is also a prefix.
+    fail: Vec<usize>,
+    /// `(value, length in chars)` of every word ending at this state.
+    out: Vec<Vec<(u32, usize)>>,
 }
 
-fn find_last(v: &Vec<char>) -> i32 {
-    let mut i = v.len() - 1;
-    loop {
-        if v[i].is_digit(10) {
-            return v[i].to_digit(10).unwrap() as i32;
+impl Scanner {
+    fn new(words: &[(&str, u32)]) -> Result<Self, String> {
+        let mut scanner = Scanner {
+            goto: vec![HashMap::new()],
+            fail: vec![0],
+            out: vec![Vec::new()],
+        };
+        for (word, value) in words.iter() {
+            if word.is_empty() {
+                return Err(format!("empty word for {value}"));
+            }
+            let mut state = 0;
+            for c in word.chars() {
+                state = match scanner.goto[state].get(&c) {
+                    Some(next) => *next,
+                    None => {
+                        scanner.goto.push(HashMap::new());
+                        scanner.fail.push(0);
+                        scanner.out.push(Vec::new());
+                        let next = scanner.goto.len() - 1;
+                        scanner.goto[state].insert(c, next);
+                        next
+                    }
+                };
+            }
+            match scanner.out[state].first() {
+                Some((v, _)) if v != value => {
+                    return Err(format!("{word:?} means both {v} and {value}"));
+                }
+                Some(_) => {}
+                None => scanner.out[state].push((*value, word.chars().count())),
+            }
         }
-        if let Some((c, _)) = word_number_at_index(v, i) {
-            return c.to_digit(10).unwrap() as i32;
+        // Breadth first, so every shorter suffix already has its fail link.
+        let mut queue = scanner.goto[0].values().copied().collect::<VecDeque<_>>();
+        while let Some(state) = queue.pop_front() {
+            let edges = scanner.goto[state]
+                .iter()
+                .map(|(c, s)| (*c, *s))
+                .collect::<Vec<_>>();
+            for (c, next) in edges {
+                let mut f = scanner.fail[state];
+                while f != 0 && !scanner.goto[f].contains_key(&c) {
+                    f = scanner.fail[f];
+                }
+                let fail = scanner.goto[f].get(&c).copied().unwrap_or(0);
+                scanner.fail[next] = fail;
+                let inherited = scanner.out[fail].clone();
+                scanner.out[next].extend(inherited);
+                queue.push_back(next);
+            }
         }
-        i -= 1;
+        Ok(scanner)
     }
-}
 
-fn word_number_at_index(v: &Vec<char>, i: usize) -> Option<(char, usize)> {
-    let nums = [
-        ("one", '1', 3),
-        ("two", '2', 3),
-        ("three", '3', 5),
-        ("four", '4', 4),
-        ("five", '5', 4),
-        ("six", '6', 3),
-        ("seven", '7', 5),
-        ("eight", '8', 5),
-        ("nine", '9', 4),
-    ];
-    for (needle, out, len) in nums {
-        if is_string_at_index(v, needle, i) {
-            return Some((out, len));
+    /// Every match as `(start char index, value)`, in order of where they end.
+    fn matches(&self, line: &str) -> Vec<(usize, u32)> {
+        let mut found = Vec::new();
+        let mut state = 0;
+        for (i, c) in line.chars().enumerate() {
+            while state != 0 && !self.goto[state].contains_key(&c) {
+                state = self.fail[state];
+            }
+            state = self.goto[state].get(&c).copied().unwrap_or(0);
+            for (value, len) in self.out[state].iter() {
+                found.push((i + 1 - len, *value));
+            }
         }
+        found
     }
-    None
-}
 
-fn is_string_at_index(v: &Vec<char>, needle: &str, i: usize) -> bool {
-    let cs = needle.chars().collect::<Vec<_>>();
-    for k in i..i + needle.len() {
-        if k >= v.len() {
-            return false;
-        }
-        if v[k] != cs[k - i] {
-            return false;
-        }
+    /// Values of the earliest and latest starting matches.
+    fn first_last(&self, line: &str) -> Option<(u32, u32)> {
+        let found = self.matches(line);
+        let first = found.iter().min_by_key(|(start, _)| *start)?;
+        let last = found.iter().max_by_key(|(start, _)| *start)?;
+        Some((first.1, last.1))
     }
-    true
 }
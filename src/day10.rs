@@ -1,4 +1,6 @@
 use std::collections::{HashMap, VecDeque};
+use std::fs;
+use std::path::Path;
 
 const DATA: &str = include_str!("day10.txt");
 /*
@@ -95,49 +97,119 @@ fn offset(pos: (usize, usize), rows: usize, cols: usize, dir: Dir) -> Option<(us
     }
 }
 
+type Grid = Vec<Vec<Pipe>>;
+
+const SHAPES: [Pipe; 6] = [Pipe::Ns, Pipe::Ew, Pipe::Ne, Pipe::Nw, Pipe::Sw, Pipe::Se];
+
 pub fn part1() -> Option<()> {
-    let mut start_pos = (0_usize, 0_usize);
-    let mut grid = Vec::new();
-    let mut row_idx = 0_usize;
-    for line in DATA.lines() {
-        let mut col = 0_usize;
-        let mut row = Vec::new();
-        for c in line.chars() {
-            match c {
-                '|' => row.push(Pipe::Ns),
-                '-' => row.push(Pipe::Ew),
-                'L' => row.push(Pipe::Ne),
-                'J' => row.push(Pipe::Nw),
-                '7' => row.push(Pipe::Sw),
-                'F' => row.push(Pipe::Se),
-                'S' => row.push(Pipe::Start),
-                '.' => row.push(Pipe::None),
-                _ => panic!("bad input: {}", c),
-            }
-            if c == 'S' {
-                start_pos = (row_idx, col);
-            }
-            col += 1;
+    let (grid, start_pos) = parse(DATA);
+    let start_kind = infer_start(&grid, start_pos)
+        .map_err(|e| eprintln!("{e}"))
+        .ok()?;
+    let result = find_loop(start_pos, start_kind, &grid)?;
+    println!("result = {result}");
+    Some(())
+}
+
+pub fn part2() -> Option<()> {
+    let (grid, start_pos) = parse(DATA);
+    let start_kind = infer_start(&grid, start_pos)
+        .map_err(|e| eprintln!("{e}"))
+        .ok()?;
+    let result = enclosed(METHOD, &grid, start_pos, start_kind);
+    println!("result = {result}");
+
+    Some(())
+}
+
+/// How part 2 counts enclosed tiles.
+const METHOD: Method = Method::Shoelace;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Method {
+    Scanline,
+    Shoelace,
+}
+
+fn enclosed(method: Method, grid: &Grid, start: (usize, usize), start_kind: Pipe) -> usize {
+    let rows = grid.len();
+    let cols = grid[0].len();
+    match method {
+        Method::Scanline => {
+            let pipe_loop = get_loop(start, start_kind, grid);
+            let new_loop = find_answer(rows, cols, &pipe_loop, grid, start_kind);
+            new_loop.values().filter(|v| **v == 3).count()
+        }
+        Method::Shoelace => {
+            let path = loop_path(start, start_kind, grid).expect("start does not close the loop");
+            let vertices = loop_vertices(&path, grid, start_kind);
+            // Shoelace gives the area of the polygon through the tile centres,
+            // Pick's theorem turns that into the count of interior points.
+            let twice_area = vertices
+                .iter()
+                .zip(vertices.iter().cycle().skip(1))
+                .map(|(a, b)| a.0 as i64 * b.1 as i64 - b.0 as i64 * a.1 as i64)
+                .sum::<i64>()
+                .unsigned_abs() as usize;
+            (twice_area + 2 - path.len()) / 2
         }
-        grid.push(row);
-        row_idx += 1;
     }
-    for start_kind in [Pipe::Ns, Pipe::Ew, Pipe::Ne, Pipe::Nw, Pipe::Sw, Pipe::Se] {
-        let result = find_loop(start_pos, start_kind, &grid);
-        println!("result with {:?} = {result:?}", start_kind);
+}
+
+/// The loop tiles in walking order, starting (and not repeating) at `start`,
+/// or None if following the pipes from `start` as `start_kind` does not lead
+/// back around to it.
+fn loop_path(start: (usize, usize), start_kind: Pipe, grid: &Grid) -> Option<Vec<(usize, usize)>> {
+    let rows = grid.len();
+    let cols = grid[0].len();
+    let pipe_at = |(r, c): (usize, usize)| {
+        if (r, c) == start {
+            start_kind
+        } else {
+            grid[r][c]
+        }
+    };
+    let mut path = vec![start];
+    let mut dir = start_kind.dirs()?[0];
+    let mut curr = start;
+    loop {
+        let next = offset(curr, rows, cols, dir)?;
+        if pipe_at(curr).can_connect(&pipe_at(next), dir) != Some(true) {
+            return None;
+        }
+        if next == start {
+            return Some(path);
+        }
+        if path.len() == rows * cols {
+            return None;
+        }
+        path.push(next);
+        let came_from = dir.opposite();
+        dir = *pipe_at(next).dirs()?.iter().find(|d| **d != came_from)?;
+        curr = next;
     }
-    Some(())
 }
 
-pub fn part2() -> Option<()> {
-    // From part 1 => Start = Sw
+/// The corners of the loop, in walking order.
+fn loop_vertices(path: &[(usize, usize)], grid: &Grid, start_kind: Pipe) -> Vec<(usize, usize)> {
+    path.iter()
+        .copied()
+        .filter(|(r, c)| {
+            let p = match grid[*r][*c] {
+                Pipe::Start => start_kind,
+                p => p,
+            };
+            p != Pipe::Ns && p != Pipe::Ew
+        })
+        .collect()
+}
+
+fn parse(input: &str) -> (Grid, (usize, usize)) {
     let mut start_pos = (0_usize, 0_usize);
     let mut grid = Vec::new();
-    let mut row_idx = 0_usize;
-    for line in DATA.lines() {
-        let mut col = 0_usize;
+    for (row_idx, line) in input.lines().enumerate() {
         let mut row = Vec::new();
-        for c in line.chars() {
+        for (col, c) in line.chars().enumerate() {
             match c {
                 '|' => row.push(Pipe::Ns),
                 '-' => row.push(Pipe::Ew),
@@ -152,30 +224,30 @@ pub fn part2() -> Option<()> {
             if c == 'S' {
                 start_pos = (row_idx, col);
             }
-            col += 1;
         }
         grid.push(row);
-        row_idx += 1;
-    }
-    let start_kind = Pipe::Sw;
-    let pipe_loop = get_loop(start_pos, start_kind, &grid);
-    let mut result = 0;
-    let rows = grid.len();
-    let cols = grid[0].len();
-    print_loop(rows, cols, &pipe_loop);
-    let new_loop = find_answer(rows, cols, &pipe_loop, &grid, start_kind);
-    println!("\n\n-----\n\n");
-    print_answer(rows, cols, &new_loop);
-    for r in 0..rows {
-        for c in 0..cols {
-            if let Some(&3) = new_loop.get(&(r, c)) {
-                result += 1;
-            }
-        }
     }
-    println!("result = {result}");
+    (grid, start_pos)
+}
 
-    Some(())
+/// Works out which pipe is hidden under `S` by trying every shape and
+/// keeping the ones whose pipes lead all the way around back to it. Stray
+/// pipes that merely point at `S` don't count; anything other than exactly
+/// one closing shape is an error.
+fn infer_start(grid: &Grid, start: (usize, usize)) -> Result<Pipe, String> {
+    let shapes = SHAPES
+        .into_iter()
+        .filter(|kind| loop_path(start, *kind, grid).is_some())
+        .collect::<Vec<_>>();
+    match shapes.as_slice() {
+        [kind] => Ok(*kind),
+        [] => Err(format!(
+            "no pipe shape closes a loop at the start {start:?}"
+        )),
+        _ => Err(format!(
+            "start at {start:?} is ambiguous, could be any of {shapes:?}"
+        )),
+    }
 }
 
 fn find_answer(
@@ -212,33 +284,63 @@ fn find_answer(
     helper
 }
 
-fn print_loop(rows: usize, cols: usize, pipe_loop: &HashMap<(usize, usize), i32>) {
-    for r in 0..rows {
-        for c in 0..cols {
-            match pipe_loop.get(&(r, c)) {
-                Some(0) => print!("S"),
-                Some(_) => print!("X"),
-                None => print!("."),
-            }
+impl Pipe {
+    fn box_char(&self) -> char {
+        match self {
+            Pipe::Ns => '│',
+            Pipe::Ew => '─',
+            Pipe::Ne => '└',
+            Pipe::Nw => '┘',
+            Pipe::Sw => '┐',
+            Pipe::Se => '┌',
+            Pipe::Start => 'S',
+            Pipe::None => ' ',
         }
-        println!("");
     }
 }
 
-fn print_answer(rows: usize, cols: usize, pipe_loop: &HashMap<(usize, usize), i32>) {
-    for r in 0..rows {
-        for c in 0..cols {
-            match pipe_loop.get(&(r, c)) {
-                Some(0) => print!(" "),
-                Some(1) => print!("~"),
-                Some(2) => print!("."),
-                Some(3) => print!("*"),
-                Some(_) => print!("_"),
-                None => print!("."),
-            }
+/// Draws the loop with box characters and every other tile as `I` (inside)
+/// or `O` (outside). With `color` the start, inside and outside tiles are
+/// highlighted with ANSI escapes.
+fn render_loop(grid: &Grid, start: (usize, usize), start_kind: Pipe, color: bool) -> String {
+    let rows = grid.len();
+    let cols = grid[0].len();
+    let pipe_loop = get_loop(start, start_kind, grid);
+    let tiles = find_answer(rows, cols, &pipe_loop, grid, start_kind);
+    let paint = |code: &str, c: char| {
+        if color {
+            format!("\x1b[{code}m{c}\x1b[0m")
+        } else {
+            c.to_string()
         }
-        println!("");
+    };
+    let mut out = String::new();
+    for (r, row) in grid.iter().enumerate() {
+        for (c, pipe) in row.iter().enumerate() {
+            out.push_str(&match tiles.get(&(r, c)) {
+                Some(1) if (r, c) == start => paint("1;33", start_kind.box_char()),
+                Some(1) => pipe.box_char().to_string(),
+                Some(3) => paint("32", 'I'),
+                _ => paint("2", 'O'),
+            });
+        }
+        out.push('\n');
     }
+    out
+}
+
+pub fn render(path: Option<&Path>) -> Option<()> {
+    let (grid, start_pos) = parse(DATA);
+    let start_kind = infer_start(&grid, start_pos)
+        .map_err(|e| eprintln!("{e}"))
+        .ok()?;
+    match path {
+        Some(path) => fs::write(path, render_loop(&grid, start_pos, start_kind, false))
+            .map_err(|e| eprintln!("cannot write {}: {e}", path.display()))
+            .ok()?,
+        None => print!("{}", render_loop(&grid, start_pos, start_kind, true)),
+    }
+    Some(())
 }
 
 fn find_loop(start: (usize, usize), start_kind: Pipe, grid: &Vec<Vec<Pipe>>) -> Option<i32> {
@@ -299,3 +401,85 @@ fn get_loop(
     }
     paths
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn start_ignores_stray_pipes() {
+        let (grid, start) = parse("..|..\n.FS7.\n.|.|.\n.L-J.\n.....");
+        assert_eq!(infer_start(&grid, start), Ok(Pipe::Ew));
+    }
+
+    fn assert_enclosed(input: &str, expected: usize) {
+        let (grid, start) = parse(input);
+        let kind = infer_start(&grid, start).unwrap();
+        for method in [Method::Scanline, Method::Shoelace] {
+            assert_eq!(enclosed(method, &grid, start, kind), expected, "{method:?}");
+        }
+    }
+
+    #[test]
+    fn enclosed_examples() {
+        assert_enclosed(
+            "\
+...........
+.S-------7.
+.|F-----7|.
+.||.....||.
+.||.....||.
+.|L-7.F-J|.
+.|..|.|..|.
+.L--J.L--J.
+...........",
+            4,
+        );
+        assert_enclosed(
+            "\
+..........
+.S------7.
+.|F----7|.
+.||....||.
+.||....||.
+.|L-7F-J|.
+.|..||..|.
+.L--JL--J.
+..........",
+            4,
+        );
+        assert_enclosed(
+            "\
+.F----7F7F7F7F-7....
+.|F--7||||||||FJ....
+.||.FJ||||||||L7....
+FJL7L7LJLJ||LJ.L-7..
+L--J.L7...LJS7F-7L7.
+....F-J..F7FJ|L7L7L7
+....L7.F7||L7|.L7L7|
+.....|FJLJ|FJ|F7|.LJ
+....FJL-7.||.||||...
+....L---J.LJ.LJLJ...",
+            8,
+        );
+        assert_enclosed(
+            "\
+FF7FSF7F7F7F7F7F---7
+L|LJ||||||||||||F--J
+FL-7LJLJ||||||LJL-77
+F--JF--7||LJLJ7F7FJ-
+L---JF-JLJ.||-FJLJJ7
+|F|F-JF---7F7-L7L|7|
+|FFJF7L7F-JF7|JL---7
+7-L-JL7||F7|L7F-7F7|
+L.L7LFJ|||||FJL7||LJ
+L7JLJL-JLJLJL--JLJ.L",
+            10,
+        );
+    }
+
+    #[test]
+    fn enclosed_input() {
+        assert_enclosed(DATA, 401);
+    }
+}
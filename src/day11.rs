@@ -1,103 +1,104 @@
-use std::collections::HashSet;
-
 const DATA: &str = include_str!("day11.txt");
 
+type Pos = (usize, usize);
+
 pub fn part1() -> Option<()> {
-    let mut result = 0;
-    let mut space = Vec::new();
-    let mut empty_rows = Vec::new();
-    let mut empty_cols = Vec::new();
-    let mut galaxies = HashSet::new();
-    for line in DATA.lines() {
-        let mut row = Vec::new();
-        let mut col = 0;
-        for c in line.chars() {
-            let galaxy = c == '#';
-            row.push(galaxy);
-            if galaxy {
-                galaxies.insert((space.len(), col));
-            }
-            col += 1;
-        }
-        space.push(row);
-    }
-    for (i, r) in space.iter().enumerate() {
-        if !r.iter().any(|x| *x) {
-            empty_rows.push(i);
-        }
-    }
-    'outer: for c in 0..space[0].len() {
-        for r in 0..space.len() {
-            if space[r][c] {
-                continue 'outer;
-            }
-        }
-        empty_cols.push(c);
-    }
-    let mut reals = Vec::new();
-    for (r, c) in galaxies.iter() {
-        let rr = r + empty_rows.iter().take_while(|i| **i < *r).count();
-        let cc = c + empty_cols.iter().take_while(|i| **i < *c).count();
-        reals.push((rr, cc));
-    }
-    for i in 0..reals.len() {
-        for j in i + 1..reals.len() {
-            let x = reals[i];
-            let y = reals[j];
-            let dist = x.0.abs_diff(y.0) + x.1.abs_diff(y.1);
-            result += dist;
-        }
-    }
+    let galaxies = parse(DATA).expand(2)?;
+    let result = total_distance(&galaxies);
     println!("result = {result}");
     Some(())
 }
 
 pub fn part2() -> Option<()> {
-    let mut result = 0;
+    let galaxies = parse(DATA).expand(1000000)?;
+    let result = total_distance(&galaxies);
+    println!("result = {result}");
+    Some(())
+}
+
+struct Image {
+    galaxies: Vec<Pos>,
+    empty_rows: Vec<usize>,
+    empty_cols: Vec<usize>,
+}
+
+fn parse(input: &str) -> Image {
     let mut space = Vec::new();
-    let mut empty_rows = Vec::new();
-    let mut empty_cols = Vec::new();
-    let mut galaxies = HashSet::new();
-    for line in DATA.lines() {
+    let mut galaxies = Vec::new();
+    for line in input.lines() {
         let mut row = Vec::new();
-        let mut col = 0;
-        for c in line.chars() {
+        for (col, c) in line.chars().enumerate() {
             let galaxy = c == '#';
             row.push(galaxy);
             if galaxy {
-                galaxies.insert((space.len(), col));
+                galaxies.push((space.len(), col));
             }
-            col += 1;
         }
         space.push(row);
     }
-    for (i, r) in space.iter().enumerate() {
-        if !r.iter().any(|x| *x) {
-            empty_rows.push(i);
-        }
+    let empty_rows = (0..space.len())
+        .filter(|r| !space[*r].iter().any(|x| *x))
+        .collect();
+    let empty_cols = (0..space[0].len())
+        .filter(|c| !space.iter().any(|row| row[*c]))
+        .collect();
+    Image {
+        galaxies,
+        empty_rows,
+        empty_cols,
     }
-    'outer: for c in 0..space[0].len() {
-        for r in 0..space.len() {
-            if space[r][c] {
-                continue 'outer;
-            }
-        }
-        empty_cols.push(c);
-    }
-    let mut reals = Vec::new();
-    for (r, c) in galaxies.iter() {
-        let rr = r + empty_rows.iter().take_while(|i| **i < *r).count() * (1000000 - 1);
-        let cc = c + empty_cols.iter().take_while(|i| **i < *c).count() * (1000000 - 1);
-        reals.push((rr, cc));
+}
+
+impl Image {
+    /// Galaxy positions, in reading order, after every empty row and column
+    /// has been replaced by `factor` of them. None for a factor of 0, or if a
+    /// position overflows.
+    fn expand(&self, factor: usize) -> Option<Vec<Pos>> {
+        let extra = factor.checked_sub(1)?;
+        self.galaxies
+            .iter()
+            .map(|(r, c)| {
+                let shift = |empty: &[usize], v: &usize| {
+                    empty
+                        .partition_point(|i| i < v)
+                        .checked_mul(extra)?
+                        .checked_add(*v)
+                };
+                Some((shift(&self.empty_rows, r)?, shift(&self.empty_cols, c)?))
+            })
+            .collect()
     }
-    for i in 0..reals.len() {
-        for j in i + 1..reals.len() {
-            let x = reals[i];
-            let y = reals[j];
-            let dist = x.0.abs_diff(y.0) + x.1.abs_diff(y.1);
-            result += dist;
+}
+
+/// Sum of Manhattan distances over all pairs. The axes are independent, so
+/// each one is sorted and every value contributes its distance to all the
+/// smaller values before it via a running prefix sum.
+fn total_distance(galaxies: &[Pos]) -> usize {
+    let axis_sum = |mut vs: Vec<usize>| {
+        vs.sort_unstable();
+        let mut prefix = 0;
+        let mut total = 0;
+        for (i, v) in vs.into_iter().enumerate() {
+            total += v * i - prefix;
+            prefix += v;
         }
-    }
-    println!("result = {result}");
+        total
+    };
+    axis_sum(galaxies.iter().map(|g| g.0).collect())
+        + axis_sum(galaxies.iter().map(|g| g.1).collect())
+}
+
+/// Distance between galaxies `a` and `b`, numbered from 1 in reading order
+/// as in the puzzle statement.
+fn distance(galaxies: &[Pos], a: usize, b: usize) -> Option<usize> {
+    let x = galaxies.get(a.checked_sub(1)?)?;
+    let y = galaxies.get(b.checked_sub(1)?)?;
+    Some(x.0.abs_diff(y.0) + x.1.abs_diff(y.1))
+}
+
+pub fn print_distance(a: usize, b: usize, factor: usize) -> Option<()> {
+    let galaxies = parse(DATA).expand(factor)?;
+    let result = distance(&galaxies, a, b)?;
+    println!("distance from {a} to {b} = {result}");
     Some(())
 }
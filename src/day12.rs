@@ -1,25 +1,48 @@
 const DATA: &str = include_str!("day12.txt");
-use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
 
-pub fn part1() -> Option<()> {
-    let mut result = 0;
-    for line in DATA.lines() {
-        let ans = arrangements(line, 1)?;
-        result += ans;
+use crate::parallel;
+
+/// How many copies of each row part 2 joins together.
+static UNFOLD: AtomicUsize = AtomicUsize::new(5);
+
+pub fn set_unfold(size: usize) -> Result<(), String> {
+    if size == 0 {
+        return Err("unfold factor must be at least 1".to_string());
     }
+    UNFOLD.store(size, Ordering::Relaxed);
+    Ok(())
+}
+
+pub fn part1() -> Option<()> {
+    let result = total_arrangements(DATA, 1)
+        .map_err(|e| eprintln!("{e}"))
+        .ok()?;
     println!("result = {result}");
     Some(())
 }
 
 pub fn part2() -> Option<()> {
-    let mut result = 0;
-    for line in DATA.lines() {
-        result += arrangements(line, 5)?;
-    }
+    let result = total_arrangements(DATA, UNFOLD.load(Ordering::Relaxed))
+        .map_err(|e| eprintln!("{e}"))
+        .ok()?;
     println!("result = {result}");
     Some(())
 }
 
+fn total_arrangements(input: &str, size: usize) -> Result<usize, String> {
+    let lines = input.lines().collect::<Vec<_>>();
+    parallel::map_reduce(
+        &lines,
+        |line| arrangements(line, size),
+        Ok(0),
+        |a, b| {
+            a?.checked_add(b?)
+                .ok_or_else(|| "total arrangements overflow usize".to_string())
+        },
+    )
+}
+
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 enum Spring {
     Oper,
@@ -47,27 +70,61 @@ impl Spring {
     }
 }
 
-impl From<char> for Spring {
-    fn from(c: char) -> Self {
+impl TryFrom<char> for Spring {
+    type Error = String;
+
+    fn try_from(c: char) -> Result<Self, Self::Error> {
         match c {
-            '#' => Spring::Broken,
-            '.' => Spring::Oper,
-            '?' => Spring::Unknown,
-            _ => panic!("bad input: {c}"),
+            '#' => Ok(Spring::Broken),
+            '.' => Ok(Spring::Oper),
+            '?' => Ok(Spring::Unknown),
+            _ => Err(format!("bad spring {c:?}")),
         }
     }
 }
 
-fn parse_input(input: &str) -> Option<(Vec<Spring>, Vec<usize>)> {
-    let (spring_data, count_data) = input.split_once(' ')?;
+fn parse_input(input: &str) -> Result<(Vec<Spring>, Vec<usize>), String> {
+    let (spring_data, count_data) = input
+        .split_once(' ')
+        .ok_or_else(|| format!("expected springs and counts separated by a space: {input:?}"))?;
     let counts = count_data
         .split(',')
-        .flat_map(|x| x.parse::<usize>().ok())
-        .collect::<Vec<_>>();
+        .map(|x| {
+            x.parse::<usize>()
+                .map_err(|_| format!("bad count {x:?} in {input:?}"))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
 
-    let springs = spring_data.chars().map(From::from).collect::<Vec<Spring>>();
+    let springs = spring_data
+        .chars()
+        .map(|c| Spring::try_from(c).map_err(|e| format!("{e} in {input:?}")))
+        .collect::<Result<Vec<_>, _>>()?;
 
-    Some((springs, counts))
+    Ok((springs, counts))
+}
+
+/// Checks that a row with no unknown springs has exactly the given groups.
+fn validate(springs: &[Spring], counts: &[usize]) -> Result<(), String> {
+    if let Some(i) = springs.iter().position(|s| *s == Spring::Unknown) {
+        return Err(format!("spring {i} is unknown in {}", render(springs)));
+    }
+    let groups = springs
+        .split(|s| !s.is_broken())
+        .map(|g| g.len())
+        .filter(|len| *len > 0)
+        .collect::<Vec<_>>();
+    if groups != counts {
+        return Err(format!(
+            "groups {groups:?} in {} do not match counts {counts:?}",
+            render(springs)
+        ));
+    }
+    Ok(())
+}
+
+pub fn check_row(line: &str) -> Result<(), String> {
+    let (springs, counts) = parse_input(line)?;
+    validate(&springs, &counts)
 }
 
 fn expand(springs: Vec<Spring>, counts: Vec<usize>, size: usize) -> (Vec<Spring>, Vec<usize>) {
@@ -81,59 +138,191 @@ fn expand(springs: Vec<Spring>, counts: Vec<usize>, size: usize) -> (Vec<Spring>
     (output_springs, output_counts)
 }
 
-fn arrangements(input: &str, size: usize) -> Option<usize> {
+fn arrangements(input: &str, size: usize) -> Result<usize, String> {
     let (springs, counts) = parse_input(input)?;
     let (springs, counts) = expand(springs, counts, size);
-    let mut cache = HashMap::new();
-    Some(dp(springs.as_slice(), counts.as_slice(), &mut cache))
+    Ok(Arrangements::new(springs, counts)
+        .map_err(|e| format!("{e} in {input:?}"))?
+        .count())
 }
 
-#[derive(Debug, Hash, Eq, PartialEq)]
-struct Key<'a> {
-    springs: &'a [Spring],
-    counts: &'a [usize],
+fn render(springs: &[Spring]) -> String {
+    springs
+        .iter()
+        .map(|s| match s {
+            Spring::Broken => '#',
+            Spring::Oper => '.',
+            Spring::Unknown => '?',
+        })
+        .collect()
 }
 
-fn is_broken(springs: &[Spring], count: usize) -> bool {
-    springs.len() >= count
-        && springs.iter().take(count).all(Spring::is_maybe_broken)
-        && (springs.len() == count || !springs[count].is_broken())
+/// Tabulated arrangement counts for a row: `ways[i][j]` is the number of
+/// ways to place groups `counts[j..]` into `springs[i..]`.
+struct Arrangements {
+    springs: Vec<Spring>,
+    counts: Vec<usize>,
+    ways: Vec<Vec<usize>>,
 }
 
-fn is_operational(springs: &[Spring]) -> bool {
-    springs.iter().all(Spring::is_maybe_operational)
-}
+impl Arrangements {
+    fn new(springs: Vec<Spring>, counts: Vec<usize>) -> Result<Self, String> {
+        let n = springs.len();
+        let m = counts.len();
+        let mut arr = Arrangements {
+            springs,
+            counts,
+            ways: vec![vec![0; m + 1]; n + 1],
+        };
+        arr.ways[n][m] = 1;
+        for i in (0..n).rev() {
+            for j in (0..=m).rev() {
+                let mut total = 0usize;
+                if arr.springs[i].is_maybe_operational() {
+                    total = arr.ways[i + 1][j];
+                }
+                if let Some(next) = arr.place(i, j) {
+                    total = total
+                        .checked_add(arr.ways[next][j + 1])
+                        .ok_or_else(|| "arrangement count overflows usize".to_string())?;
+                }
+                arr.ways[i][j] = total;
+            }
+        }
+        Ok(arr)
+    }
+
+    fn count(&self) -> usize {
+        self.ways[0][0]
+    }
+
+    /// If group `j` can start at spring `i`, the index just past the group
+    /// and its trailing operational spring.
+    fn place(&self, i: usize, j: usize) -> Option<usize> {
+        let count = *self.counts.get(j)?;
+        let end = i + count;
+        if end > self.springs.len() || !self.springs[i..end].iter().all(Spring::is_maybe_broken) {
+            return None;
+        }
+        match self.springs.get(end) {
+            None => Some(end),
+            Some(s) if s.is_maybe_operational() => Some(end + 1),
+            Some(_) => None,
+        }
+    }
 
-fn dp<'a>(
-    springs: &'a [Spring],
-    counts: &'a [usize],
-    cache: &mut HashMap<Key<'a>, usize>,
-) -> usize {
-    if springs.is_empty() {
-        return if counts.is_empty() { 1 } else { 0 };
+    /// Every concrete arrangement of the row, in lexicographic order with
+    /// operational before broken.
+    fn enumerate(&self) -> Vec<Vec<Spring>> {
+        let mut out = Vec::with_capacity(self.count());
+        let mut current = Vec::with_capacity(self.springs.len());
+        self.enumerate_from(0, 0, &mut current, &mut out);
+        out
     }
-    if counts.is_empty() {
-        return if is_operational(springs) { 1 } else { 0 };
+
+    fn enumerate_from(
+        &self,
+        i: usize,
+        j: usize,
+        current: &mut Vec<Spring>,
+        out: &mut Vec<Vec<Spring>>,
+    ) {
+        if self.ways[i][j] == 0 {
+            return;
+        }
+        if i == self.springs.len() {
+            out.push(current.clone());
+            return;
+        }
+        let len = current.len();
+        if self.springs[i].is_maybe_operational() {
+            current.push(Spring::Oper);
+            self.enumerate_from(i + 1, j, current, out);
+            current.truncate(len);
+        }
+        if let Some(next) = self.place(i, j) {
+            self.push_group(current, i, j, next);
+            self.enumerate_from(next, j + 1, current, out);
+            current.truncate(len);
+        }
     }
 
-    let key = Key { springs, counts };
-    if let Some(t) = cache.get(&key) {
-        return *t;
+    fn push_group(&self, current: &mut Vec<Spring>, i: usize, j: usize, next: usize) {
+        current.extend(std::iter::repeat_n(Spring::Broken, self.counts[j]));
+        if next > i + self.counts[j] {
+            current.push(Spring::Oper);
+        }
     }
 
-    let mut total = 0;
-    if is_broken(springs, counts[0]) {
-        let offset = if springs.len() == counts[0] {
-            counts[0]
-        } else {
-            counts[0] + 1
-        };
-        total += dp(&springs[offset..], &counts[1..], cache);
+    /// Picks one arrangement uniformly at random by walking the table and
+    /// choosing each branch with probability proportional to its count.
+    fn sample(&self, rng: &mut Rng) -> Option<Vec<Spring>> {
+        if self.count() == 0 {
+            return None;
+        }
+        let mut out = Vec::with_capacity(self.springs.len());
+        let (mut i, mut j) = (0, 0);
+        while i < self.springs.len() {
+            let oper = if self.springs[i].is_maybe_operational() {
+                self.ways[i + 1][j]
+            } else {
+                0
+            };
+            if rng.below(self.ways[i][j]) < oper {
+                out.push(Spring::Oper);
+                i += 1;
+            } else {
+                let next = self.place(i, j)?;
+                self.push_group(&mut out, i, j, next);
+                i = next;
+                j += 1;
+            }
+        }
+        Some(out)
     }
-    if springs[0].is_maybe_operational() {
-        total += dp(&springs[1..], counts, cache);
+}
+
+/// Small xorshift generator so sampling doesn't need an external crate.
+struct Rng(u64);
+
+impl Rng {
+    fn new(seed: u64) -> Self {
+        Rng(seed.max(1))
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
     }
+}
 
-    cache.insert(key, total);
-    total
+pub fn debug_row(line: &str, size: usize, samples: usize) -> Option<()> {
+    let (springs, counts) = parse_input(line).map_err(|e| eprintln!("{e}")).ok()?;
+    let (springs, counts) = expand(springs, counts, size);
+    let arr = Arrangements::new(springs, counts)
+        .map_err(|e| eprintln!("{e}"))
+        .ok()?;
+    println!(
+        "{} {:?} => {}",
+        render(&arr.springs),
+        arr.counts,
+        arr.count()
+    );
+    if arr.count() <= 100 {
+        for a in arr.enumerate() {
+            debug_assert!(validate(&a, &arr.counts).is_ok());
+            println!("  {}", render(&a));
+        }
+    }
+    let mut rng = Rng::new(0x9e3779b97f4a7c15);
+    for _ in 0..samples {
+        println!("  sample: {}", render(&arr.sample(&mut rng)?));
+    }
+    Some(())
 }
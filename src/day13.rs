@@ -43,89 +43,195 @@ fn parse(input: &str) -> Vec<Vec<Vec<usize>>> {
     grids
 }
 
-fn compute_row_hash(s: &Vec<Vec<usize>>, row: usize) -> usize {
-    let mut h = 0;
-    for v in s[row].iter() {
-        h = h << 1 | *v;
+/// A row or column packed into 64-bit words, so patterns can be any size.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct Bits {
+    words: Vec<u64>,
+}
+
+impl Bits {
+    fn from_cells<'a>(cells: impl Iterator<Item = &'a usize>) -> Self {
+        let mut words = Vec::new();
+        for (i, v) in cells.enumerate() {
+            if i % 64 == 0 {
+                words.push(0);
+            }
+            if *v != 0 {
+                words[i / 64] |= 1 << (i % 64);
+            }
+        }
+        Bits { words }
+    }
+
+    /// Number of positions where the two differ.
+    fn distance(&self, other: &Bits) -> usize {
+        self.words
+            .iter()
+            .zip(other.words.iter())
+            .map(|(a, b)| (a ^ b).count_ones() as usize)
+            .sum()
     }
-    h
 }
 
-fn flip_hash_at_index(h: usize, index: usize) -> usize {
-    h ^ (1 << index)
+fn compute_row_hash(s: &[Vec<usize>], row: usize) -> Bits {
+    Bits::from_cells(s[row].iter())
 }
 
-fn compute_col_hash(s: &Vec<Vec<usize>>, col: usize) -> usize {
-    let mut h = 0;
-    for row in s.iter() {
-        let v = row[col];
-        h = h << 1 | v;
+fn compute_col_hash(s: &[Vec<usize>], col: usize) -> Bits {
+    Bits::from_cells(s.iter().map(|row| &row[col]))
+}
+
+/// Every mirror line, as the number of rows (or columns) before it, with the
+/// number of cells that differ across it.
+fn mirror_lines(hashes: &[Bits]) -> Vec<(usize, usize)> {
+    (1..hashes.len())
+        .map(|i| {
+            let distance = (0..i.min(hashes.len() - i))
+                .map(|j| hashes[i - j - 1].distance(&hashes[i + j]))
+                .sum();
+            (i, distance)
+        })
+        .collect()
+}
+
+/// The first horizontal mirror line, else the first vertical one, with
+/// exactly `smudges` defects.
+fn find_symmetry_with(grid: &[Vec<usize>], smudges: usize) -> Option<(usize, usize)> {
+    let r = reflections_with(grid, smudges).into_iter().next()?;
+    match r.axis {
+        Axis::Horizontal => Some((r.index, 0)),
+        Axis::Vertical => Some((0, r.index)),
     }
-    h
 }
 
-fn find_hash_symmetry(hashes: &Vec<usize>, ignoring: Option<usize>) -> Option<usize> {
-    for i in 1..hashes.len() {
-        if Some(i) == ignoring {
-            continue;
-        }
-        let mut j = 1;
-        loop {
-            if hashes[i - j] != hashes[i + j - 1] {
-                break;
-            }
-            if i - j == 0 || i + j - 1 == hashes.len() - 1 {
-                return Some(i);
-            }
-            j += 1;
+fn find_symmetry(grid: &[Vec<usize>]) -> Option<(usize, usize)> {
+    find_symmetry_with(grid, 0)
+}
+
+fn find_flipped_symmetry(grid: &[Vec<usize>]) -> Option<(usize, usize)> {
+    find_symmetry_with(grid, 1)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Axis {
+    /// Mirror between two rows, `index` rows above it.
+    Horizontal,
+    /// Mirror between two columns, `index` columns left of it.
+    Vertical,
+}
+
+/// A candidate mirror line and the pairs of mirrored cells that disagree.
+/// Flipping either cell of every pair makes the reflection perfect.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Reflection {
+    axis: Axis,
+    index: usize,
+    smudges: Vec<((usize, usize), (usize, usize))>,
+}
+
+impl Reflection {
+    fn defects(&self) -> usize {
+        self.smudges.len()
+    }
+
+    fn score(&self) -> usize {
+        match self.axis {
+            Axis::Horizontal => 100 * self.index,
+            Axis::Vertical => self.index,
         }
     }
-    None
 }
 
-fn find_symmetry(grid: &Vec<Vec<usize>>) -> Option<(usize, usize)> {
-    let row_hash = (0..grid.len())
+/// The mirror lines whose defect count passes `keep`, horizontal ones first.
+/// Lines are picked on the packed rows and columns, so cell pairs are only
+/// worked out for the ones kept.
+fn reflections_where(grid: &[Vec<usize>], keep: impl Fn(usize) -> bool) -> Vec<Reflection> {
+    let rows = grid.len();
+    let cols = grid[0].len();
+    let row_hash = (0..rows)
         .map(|row| compute_row_hash(grid, row))
-        .collect();
-    if let Some(i) = find_hash_symmetry(&row_hash, None) {
-        return Some((i, 0));
-    }
-    let col_hash = (0..grid[0].len())
+        .collect::<Vec<_>>();
+    let col_hash = (0..cols)
         .map(|col| compute_col_hash(grid, col))
-        .collect();
-    if let Some(i) = find_hash_symmetry(&col_hash, None) {
-        return Some((0, i));
+        .collect::<Vec<_>>();
+    let mut result = Vec::new();
+    for (index, _) in mirror_lines(&row_hash).into_iter().filter(|l| keep(l.1)) {
+        let mut smudges = Vec::new();
+        for d in 0..index.min(rows - index) {
+            let (a, b) = (index - 1 - d, index + d);
+            if row_hash[a] == row_hash[b] {
+                continue;
+            }
+            for (c, (x, y)) in grid[a].iter().zip(grid[b].iter()).enumerate() {
+                if x != y {
+                    smudges.push(((a, c), (b, c)));
+                }
+            }
+        }
+        result.push(Reflection {
+            axis: Axis::Horizontal,
+            index,
+            smudges,
+        });
     }
-    None
+    for (index, _) in mirror_lines(&col_hash).into_iter().filter(|l| keep(l.1)) {
+        let mut smudges = Vec::new();
+        for d in 0..index.min(cols - index) {
+            let (a, b) = (index - 1 - d, index + d);
+            if col_hash[a] == col_hash[b] {
+                continue;
+            }
+            for (r, row) in grid.iter().enumerate() {
+                if row[a] != row[b] {
+                    smudges.push(((r, a), (r, b)));
+                }
+            }
+        }
+        result.push(Reflection {
+            axis: Axis::Vertical,
+            index,
+            smudges,
+        });
+    }
+    result
 }
 
-fn find_flipped_symmetry(grid: &Vec<Vec<usize>>) -> Option<(usize, usize)> {
-    let unflipped = find_symmetry(&grid)?;
-
-    let mut row_hash: Vec<usize> = (0..grid.len())
-        .map(|row| compute_row_hash(grid, row))
-        .collect();
-    let mut col_hash: Vec<usize> = (0..grid[0].len())
-        .map(|col| compute_col_hash(grid, col))
-        .collect();
+/// Every mirror line in the pattern along with its exact defect count.
+fn reflections(grid: &[Vec<usize>]) -> Vec<Reflection> {
+    reflections_where(grid, |_| true)
+}
 
-    let rows = grid.len();
-    let cols = grid[0].len();
+fn reflections_with(grid: &[Vec<usize>], k: usize) -> Vec<Reflection> {
+    reflections_where(grid, |d| d == k)
+}
 
-    for r in 0..rows {
-        for c in 0..cols {
-            row_hash[r] = flip_hash_at_index(row_hash[r], cols - c - 1);
-            if let Some(v) = find_hash_symmetry(&row_hash, Some(unflipped.0)) {
-                return Some((v, 0));
-            }
-            row_hash[r] = flip_hash_at_index(row_hash[r], cols - c - 1);
+pub fn report(k: usize) -> Option<()> {
+    let grids = parse(DATA);
+    let mut result = 0;
+    for (i, grid) in grids.iter().enumerate() {
+        for r in reflections_with(grid, k) {
+            println!(
+                "pattern {i}: {:?} at {} smudges {:?}",
+                r.axis, r.index, r.smudges
+            );
+            result += r.score();
+        }
+    }
+    println!("result = {result}");
+    Some(())
+}
 
-            col_hash[c] = flip_hash_at_index(col_hash[c], rows - r - 1);
-            if let Some(v) = find_hash_symmetry(&col_hash, Some(unflipped.1)) {
-                return Some((0, v));
-            }
-            col_hash[c] = flip_hash_at_index(col_hash[c], rows - r - 1);
+/// Prints every mirror line of every pattern with its defect count.
+pub fn report_all() -> Option<()> {
+    for (i, grid) in parse(DATA).iter().enumerate() {
+        for r in reflections(grid) {
+            println!(
+                "pattern {i}: {:?} at {} has {} defects",
+                r.axis,
+                r.index,
+                r.defects()
+            );
         }
     }
-    None
+    Some(())
 }
@@ -1,3 +1,5 @@
+use std::collections::{BTreeMap, BTreeSet};
+
 const DATA: &str = include_str!("day15.txt");
 
 #[derive(Debug, Clone)]
@@ -22,6 +24,18 @@ impl<'a> Instr<'a> {
             Instr::Remove { orig, .. } => hash(orig),
         }
     }
+
+    fn orig(&self) -> &'a str {
+        match self {
+            Instr::Add { orig, .. } | Instr::Remove { orig, .. } => orig,
+        }
+    }
+
+    fn label(&self) -> &'a str {
+        match self {
+            Instr::Add { label, .. } | Instr::Remove { label, .. } => label,
+        }
+    }
 }
 
 type Lens<'a> = (&'a str, u32);
@@ -68,6 +82,34 @@ impl<'a> HMap<'a> {
         }
         result
     }
+
+    /// The non-empty boxes, one per line, as in the puzzle statement.
+    fn describe(&self) -> String {
+        let mut out = String::new();
+        for (i, b) in self.buckets.iter().enumerate() {
+            if b.is_empty() {
+                continue;
+            }
+            out.push_str(&format!("Box {i}:"));
+            for (label, focal) in b.iter() {
+                out.push_str(&format!(" [{label} {focal}]"));
+            }
+            out.push('\n');
+        }
+        out
+    }
+}
+
+/// Labels grouped by the box they hash to, keeping only boxes that more
+/// than one distinct label lands in.
+fn collisions<'a>(instrs: &[Instr<'a>]) -> BTreeMap<u32, BTreeSet<&'a str>> {
+    let mut boxes: BTreeMap<u32, BTreeSet<&str>> = BTreeMap::new();
+    for instr in instrs.iter() {
+        let label = instr.label();
+        boxes.entry(hash(label)).or_default().insert(label);
+    }
+    boxes.retain(|_, labels| labels.len() > 1);
+    boxes
 }
 
 pub fn part1() -> Option<()> {
@@ -91,6 +133,21 @@ pub fn part2() -> Option<()> {
     Some(())
 }
 
+pub fn inspect() -> Option<()> {
+    let instrs = parse(DATA);
+    let mut hm = HMap::new();
+    for instr in instrs.iter() {
+        hm.perform(instr.clone());
+        println!("After {:?}:", instr.orig());
+        println!("{}", hm.describe());
+    }
+    for (h, labels) in collisions(&instrs) {
+        let labels = labels.into_iter().collect::<Vec<_>>().join(", ");
+        println!("Box {h} shared by: {labels}");
+    }
+    Some(())
+}
+
 fn parse(input: &str) -> Vec<Instr<'_>> {
     input
         .trim()
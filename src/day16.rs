@@ -1,6 +1,10 @@
-use std::collections::HashSet;
-use std::sync::mpsc;
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
 use std::thread;
+use std::time::Duration;
+use std::{fs, io};
+
+use crate::parallel;
 
 const DATA: &str = include_str!("day16.txt");
 
@@ -13,6 +17,18 @@ enum Tile {
     SplitterV,     // '|'
 }
 
+impl Tile {
+    fn symbol(&self) -> char {
+        match self {
+            Tile::Empty => '.',
+            Tile::MirrorBack => '/',
+            Tile::MirrorForward => '\\',
+            Tile::SplitterV => '|',
+            Tile::SplitterH => '-',
+        }
+    }
+}
+
 type Grid = Vec<Vec<Tile>>;
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
@@ -24,6 +40,15 @@ enum Dir {
 }
 
 impl Dir {
+    fn arrow(&self) -> char {
+        match self {
+            Dir::Up => '^',
+            Dir::Down => 'v',
+            Dir::Left => '<',
+            Dir::Right => '>',
+        }
+    }
+
     fn turn(&self, t: &Tile) -> Option<Dir> {
         use Dir::*;
         use Tile::*;
@@ -111,6 +136,21 @@ pub fn part1() -> Option<()> {
     Some(())
 }
 
+pub fn visualize(frames_dir: Option<&Path>) -> Option<()> {
+    let grid = parse(DATA);
+    let energy = energize(&grid, (1, 0, Dir::Right));
+    match frames_dir {
+        Some(dir) => energy
+            .write_frames(&grid, dir)
+            .map_err(|e| eprintln!("cannot write frames to {}: {e}", dir.display()))
+            .ok()?,
+        None => energy.animate(&grid, Duration::from_millis(50)),
+    }
+    print!("{}", energy.render());
+    println!("energized = {}", energy.count());
+    Some(())
+}
+
 pub fn part2() -> Option<()> {
     let grid = parse(DATA);
     let mut starts = Vec::new();
@@ -124,31 +164,9 @@ pub fn part2() -> Option<()> {
         starts.push((0, c + 1, Dir::Down));
         starts.push((rows + 1, c + 1, Dir::Up));
     }
-    let mut results = Vec::with_capacity(starts.len());
-
-    let (tx, rx) = mpsc::channel();
-    let num_threads = std::thread::available_parallelism().ok()?.get();
-    println!("Running with {num_threads} threads");
-    thread::scope(|s| {
-        let grid = &grid;
-        let starts = &starts;
-        for i in 0..num_threads {
-            let tx1 = tx.clone();
-            s.spawn(move || {
-                for s in (i..starts.len()).step_by(num_threads) {
-                    let start = starts[s];
-                    let result = find_energy(&grid, start);
-                    tx1.send(result).unwrap();
-                }
-            });
-        }
-    });
-    drop(tx);
-
-    while let Ok(v) = rx.recv() {
-        results.push(v);
-    }
-    let result = results.into_iter().max()?;
+    let result = parallel::map(&starts, |start| find_energy(&grid, *start))
+        .into_iter()
+        .max()?;
     println!("result = {result}");
     Some(())
 }
@@ -174,34 +192,150 @@ impl FromIterator<Step> for HashSet<Beam> {
 }
 
 fn find_energy(grid: &Grid, start: (usize, usize, Dir)) -> usize {
-    let mut visited = HashSet::new();
+    energize(grid, start).count()
+}
+
+/// The result of tracing a beam through the grid: every energized cell
+/// (1-indexed, like `Beam`) with the directions beams left it in, and the
+/// beams that were active at each step.
+#[derive(Debug, Clone)]
+struct Energized {
+    rows: usize,
+    cols: usize,
+    cells: HashMap<(usize, usize), HashSet<Dir>>,
+    steps: Vec<Vec<Beam>>,
+}
+
+fn energize(grid: &Grid, start: (usize, usize, Dir)) -> Energized {
     let rows = grid.len();
     let cols = grid[0].len();
+    let mut seen = HashSet::new();
+    let mut cells: HashMap<(usize, usize), HashSet<Dir>> = HashMap::new();
+    let mut steps = Vec::new();
     let mut beams = HashSet::new();
     beams.insert(Beam {
         r: start.0,
         c: start.1,
         d: start.2,
     });
-    let mut no_change_counter = 0;
     while !beams.is_empty() {
         beams = beams
             .into_iter()
-            .flat_map(|b| b.step(&grid, rows, cols))
+            .flat_map(|b| b.step(grid, rows, cols))
+            .collect::<HashSet<Beam>>()
+            .into_iter()
+            .filter(|b| seen.insert(b.clone()))
             .collect();
-        let prev = visited.len();
-        visited.extend(beams.iter().map(|b| (b.r, b.c)));
-        let new = visited.len();
-        if prev == new {
-            no_change_counter += 1;
-        } else {
-            no_change_counter = 0;
-        }
-        if no_change_counter > 10 {
+        if beams.is_empty() {
             break;
         }
+        for b in beams.iter() {
+            cells.entry((b.r, b.c)).or_default().insert(b.d);
+        }
+        steps.push(beams.iter().cloned().collect());
+    }
+    Energized {
+        rows,
+        cols,
+        cells,
+        steps,
+    }
+}
+
+impl Energized {
+    fn count(&self) -> usize {
+        self.cells.len()
+    }
+
+    fn is_energized(&self, r: usize, c: usize) -> bool {
+        self.cells.contains_key(&(r, c))
+    }
+
+    fn directions(&self, r: usize, c: usize) -> Option<&HashSet<Dir>> {
+        self.cells.get(&(r, c))
+    }
+
+    /// The `#`/`.` energized map from the puzzle statement.
+    fn render(&self) -> String {
+        let mut out = String::new();
+        for r in 1..=self.rows {
+            for c in 1..=self.cols {
+                out.push(if self.is_energized(r, c) { '#' } else { '.' });
+            }
+            out.push('\n');
+        }
+        out
+    }
+
+    /// The beam map from the puzzle statement: mirrors and splitters as-is,
+    /// an arrow where a single beam passed, or the number of beams.
+    fn render_beams(&self, grid: &Grid) -> String {
+        let mut out = String::new();
+        for r in 1..=self.rows {
+            for c in 1..=self.cols {
+                let tile = grid[r - 1][c - 1];
+                out.push(match self.directions(r, c) {
+                    Some(ds) if tile == Tile::Empty && ds.len() == 1 => {
+                        ds.iter().next().unwrap().arrow()
+                    }
+                    Some(ds) if tile == Tile::Empty => {
+                        char::from_digit(ds.len() as u32, 10).unwrap()
+                    }
+                    _ => tile.symbol(),
+                });
+            }
+            out.push('\n');
+        }
+        out
+    }
+
+    /// One frame per step: cells energized so far as `#`, the beams active
+    /// in that step as arrows.
+    fn frames(&self, grid: &Grid) -> Vec<String> {
+        let mut lit = HashSet::new();
+        let mut frames = Vec::with_capacity(self.steps.len());
+        for beams in self.steps.iter() {
+            let heads = beams
+                .iter()
+                .map(|b| ((b.r, b.c), b.d))
+                .collect::<HashMap<_, _>>();
+            lit.extend(heads.keys().copied());
+            let mut out = String::new();
+            for r in 1..=self.rows {
+                for c in 1..=self.cols {
+                    let tile = grid[r - 1][c - 1];
+                    out.push(if let Some(d) = heads.get(&(r, c)) {
+                        d.arrow()
+                    } else if tile != Tile::Empty {
+                        tile.symbol()
+                    } else if lit.contains(&(r, c)) {
+                        '#'
+                    } else {
+                        '.'
+                    });
+                }
+                out.push('\n');
+            }
+            frames.push(out);
+        }
+        frames
+    }
+
+    fn animate(&self, grid: &Grid, delay: Duration) {
+        for (i, frame) in self.frames(grid).iter().enumerate() {
+            print!("\x1b[2J\x1b[H{frame}");
+            println!("step {}/{}", i + 1, self.steps.len());
+            thread::sleep(delay);
+        }
+    }
+
+    fn write_frames(&self, grid: &Grid, dir: &Path) -> io::Result<()> {
+        fs::create_dir_all(dir)?;
+        for (i, frame) in self.frames(grid).iter().enumerate() {
+            fs::write(dir.join(format!("frame_{i:04}.txt")), frame)?;
+        }
+        Ok(())
     }
-    visited.len()
 }
 
 fn parse(input: &str) -> Grid {
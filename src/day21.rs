@@ -1,4 +1,4 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, VecDeque};
 const DATA: &str = include_str!("day21.txt");
 
 pub fn part1() -> Option<()> {
@@ -11,69 +11,276 @@ pub fn part1() -> Option<()> {
 }
 
 fn solve(grid: &Grid, start: Pos, steps: usize) -> usize {
-    let mut curr = HashSet::new();
-    curr.insert(start);
-    for _ in 1..=steps {
-        let mut next = HashSet::new();
-        for n in curr.into_iter() {
-            for adj in neighbors(&grid, n) {
-                next.insert(adj);
+    Distances::new(grid, start).reachable(steps)
+}
+
+/// Step counts from the start to every reachable plot of a single grid.
+struct Distances {
+    dist: Vec<Vec<Option<usize>>>,
+    counts: ParityCounts,
+}
+
+impl Distances {
+    fn new(grid: &Grid, start: Pos) -> Self {
+        let mut dist = vec![vec![None; grid[0].len()]; grid.len()];
+        let mut queue = VecDeque::new();
+        dist[start.0][start.1] = Some(0);
+        queue.push_back(start);
+        while let Some(pos) = queue.pop_front() {
+            let d = dist[pos.0][pos.1].unwrap();
+            for (r, c) in neighbors(grid, pos) {
+                if dist[r][c].is_none() {
+                    dist[r][c] = Some(d + 1);
+                    queue.push_back((r, c));
+                }
             }
         }
-        curr = next;
+        let counts = ParityCounts::new(dist.iter().flatten().flatten().copied());
+        Distances { dist, counts }
     }
 
-    curr.len()
+    fn reachable(&self, steps: usize) -> usize {
+        self.counts.at(steps)
+    }
 }
 
-fn isolve(grid: &Grid, start: Pos, steps: usize) -> usize {
-    let mut curr = HashSet::new();
-    curr.insert(((0, 0), start));
-    for _ in 1..=steps {
-        let mut next = HashSet::new();
-        for n in curr.into_iter() {
-            for adj in ineighbors(&grid, n) {
-                next.insert(adj);
+/// Answers "how many plots can be reached in exactly `steps` steps" for any
+/// `steps` from a list of shortest distances. A plot at distance `d` can be
+/// reached in `steps` if `d <= steps` and it can burn the rest stepping back
+/// and forth, i.e. `d` has the same parity as `steps`.
+struct ParityCounts {
+    /// `prefix[d]` counts plots at distance `d`, `d - 2`, `d - 4`, ...
+    prefix: Vec<usize>,
+}
+
+impl ParityCounts {
+    fn new(dists: impl Iterator<Item = usize>) -> Self {
+        let mut prefix = Vec::new();
+        for d in dists {
+            if d >= prefix.len() {
+                prefix.resize(d + 1, 0);
             }
+            prefix[d] += 1;
+        }
+        for d in 2..prefix.len() {
+            prefix[d] += prefix[d - 2];
         }
-        curr = next;
+        ParityCounts { prefix }
     }
 
-    curr.len()
+    fn at(&self, steps: usize) -> usize {
+        let Some(max) = self.prefix.len().checked_sub(1) else {
+            return 0;
+        };
+        if steps <= max {
+            self.prefix[steps]
+        } else if (steps - max).is_multiple_of(2) {
+            self.prefix[max]
+        } else if max > 0 {
+            self.prefix[max - 1]
+        } else {
+            0
+        }
+    }
 }
 
 pub fn part2() -> Option<()> {
     let (grid, start) = parse(DATA);
 
     let goal = 26501365;
-    let rows = grid.len();
-    let a = goal % rows;
-
-    let b0 = isolve(&grid, start, a) as isize;
-    let b1 = isolve(&grid, start, a + rows) as isize;
-    let b2 = isolve(&grid, start, a + rows + rows) as isize;
-
-    let n = ((goal - a) / rows) as isize;
-    /*
-     * Lagrange...
-    b0 (n - 1)*(n-2) / (-1*-2) = b0*(n^2 - 3n + 2) / 2
-    b1 n*(n-2)/(-1) = -b1*(n^2 - 2n)
-    b2 n*(n-1)/2 = b2*(n^2 - n) / 2
-
-    => z0 = (b0 - 2* b1 + b2)/2
-       z1 = -3*b0/2 + 2*b1 - b2/2 = b1 - b0 - (b0 / 2) + b1 - b2/2 = b1 - b0 - z0
-       z2 = b0
-    */
-    let z0 = (b0 - 2 * b1 + b2) / 2;
-    let z1 = b1 - b0 - z0;
-    let z2 = b0;
-
-    let result = z2 + z1 * n + z0 * n * n;
+    let result = count_reachable(&grid, start, goal)?;
     println!("result = {result}");
 
     Some(())
 }
 
+const MAX_BLOCK: usize = 8;
+
+/// Step counts from the start to every plot of a `(2 pad + 1) x (2 pad + 1)`
+/// block of copies of the grid, with the start's copy in the middle.
+struct TiledDistances {
+    pad: usize,
+    rows: usize,
+    cols: usize,
+    dist: Vec<Vec<Option<usize>>>,
+    counts: ParityCounts,
+}
+
+impl TiledDistances {
+    fn new(grid: &Grid, start: Pos, pad: usize) -> Self {
+        let rows = grid.len();
+        let cols = grid[0].len();
+        let size = 2 * pad + 1;
+        let mut dist = vec![vec![None; size * cols]; size * rows];
+        let mut queue = VecDeque::new();
+        let origin = (pad * rows + start.0, pad * cols + start.1);
+        dist[origin.0][origin.1] = Some(0);
+        queue.push_back(origin);
+        while let Some((r, c)) = queue.pop_front() {
+            let d = dist[r][c].unwrap();
+            let adj = [
+                (r.wrapping_sub(1), c),
+                (r + 1, c),
+                (r, c.wrapping_sub(1)),
+                (r, c + 1),
+            ];
+            for (rr, cc) in adj {
+                if rr >= size * rows || cc >= size * cols || dist[rr][cc].is_some() {
+                    continue;
+                }
+                if is_garden(grid, (rr % rows, cc % cols)) {
+                    dist[rr][cc] = Some(d + 1);
+                    queue.push_back((rr, cc));
+                }
+            }
+        }
+        let counts = ParityCounts::new(dist.iter().flatten().flatten().copied());
+        TiledDistances {
+            pad,
+            rows,
+            cols,
+            dist,
+            counts,
+        }
+    }
+
+    /// A block big enough that nothing within `steps` of the start falls
+    /// off its edge.
+    fn covering(grid: &Grid, start: Pos, steps: usize) -> Self {
+        let pad = steps / grid.len().min(grid[0].len()) + 1;
+        TiledDistances::new(grid, start, pad)
+    }
+
+    /// Only exact for step counts the block covers, see `covering`.
+    fn reachable(&self, steps: usize) -> usize {
+        self.counts.at(steps)
+    }
+
+    /// Distance to plot `pos` in the copy `tile` copies away from the start's.
+    fn get(&self, tile: (isize, isize), pos: Pos) -> Option<usize> {
+        let r = (tile.0 + self.pad as isize) as usize * self.rows + pos.0;
+        let c = (tile.1 + self.pad as isize) as usize * self.cols + pos.1;
+        self.dist[r][c]
+    }
+
+    /// True if moving one copy outward across the border of the inner
+    /// `(2k+1) x (2k+1)` block adds exactly one grid height or width to every
+    /// distance, which is what lets the copies beyond it be counted without
+    /// visiting them.
+    fn is_stable(&self, k: isize) -> bool {
+        let shifted = |outer: (isize, isize), inner: (isize, isize), by: usize| {
+            (0..self.rows).all(|r| {
+                (0..self.cols)
+                    .all(|c| self.get(outer, (r, c)) == self.get(inner, (r, c)).map(|d| d + by))
+            })
+        };
+        (-k..=k).all(|t| {
+            shifted((k, t), (k - 1, t), self.rows)
+                && shifted((-k, t), (1 - k, t), self.rows)
+                && shifted((t, k), (t, k - 1), self.cols)
+                && shifted((t, -k), (t, 1 - k), self.cols)
+        })
+    }
+}
+
+/// Number of plots reachable in exactly `steps` steps on the infinitely
+/// repeated grid. Copies inside a stable block are counted directly; each
+/// copy on the block's border also stands in for every copy straight out
+/// beyond it (edges) or in the quadrant beyond it (corners). Gives up if the
+/// distances never settle into that pattern, which needs the grid to have
+/// some clear path around its border.
+fn count_reachable(grid: &Grid, start: Pos, steps: usize) -> Option<usize> {
+    let rows = grid.len();
+    let cols = grid[0].len();
+    // The search covers twice the block that gets counted so paths that
+    // wander outside the block are still found.
+    let (k, tiles) = (2..=MAX_BLOCK as isize)
+        .map(|k| (k, TiledDistances::new(grid, start, 2 * k as usize)))
+        .find(|(k, tiles)| tiles.is_stable(*k))?;
+
+    let mut corners = HashMap::new();
+    let mut total = 0;
+    for tr in -k..=k {
+        for tc in -k..=k {
+            for r in 0..rows {
+                for c in 0..cols {
+                    let Some(d) = tiles.get((tr, tc), (r, c)) else {
+                        continue;
+                    };
+                    if d <= steps && (steps - d).is_multiple_of(2) {
+                        total += 1;
+                    }
+                    total += match (tr.abs() == k, tc.abs() == k) {
+                        (true, true) => *corners
+                            .entry(d)
+                            .or_insert_with(|| count_corner(d, steps, rows, cols)),
+                        (true, false) => count_line(d, steps, rows),
+                        (false, true) => count_line(d, steps, cols),
+                        (false, false) => 0,
+                    };
+                }
+            }
+        }
+    }
+    Some(total)
+}
+
+/// How many `x` in `lo..=hi` have `x % 2 == parity`.
+fn count_with_parity(lo: usize, hi: usize, parity: usize) -> usize {
+    if lo > hi {
+        return 0;
+    }
+    let first = if lo % 2 == parity { lo } else { lo + 1 };
+    if first > hi {
+        0
+    } else {
+        (hi - first) / 2 + 1
+    }
+}
+
+/// Copies `n >= 1` periods further out where a plot first reached after `d`
+/// steps is reachable in exactly `steps`.
+fn count_line(d: usize, steps: usize, period: usize) -> usize {
+    if d > steps {
+        return 0;
+    }
+    let rem = steps - d;
+    let max = rem / period;
+    if period.is_multiple_of(2) {
+        if rem.is_multiple_of(2) {
+            max
+        } else {
+            0
+        }
+    } else {
+        count_with_parity(1, max, rem % 2)
+    }
+}
+
+/// Like `count_line` but over the quadrant of copies `a` rows and `b`
+/// columns of copies further out, excluding the copy itself.
+fn count_corner(d: usize, steps: usize, rows: usize, cols: usize) -> usize {
+    if d > steps {
+        return 0;
+    }
+    let mut total = 0;
+    for a in 0..=(steps - d) / rows {
+        let rem = steps - d - a * rows;
+        let lo = if a == 0 { 1 } else { 0 };
+        let hi = rem / cols;
+        total += if cols.is_multiple_of(2) {
+            if rem.is_multiple_of(2) && lo <= hi {
+                hi - lo + 1
+            } else {
+                0
+            }
+        } else {
+            count_with_parity(lo, hi, rem % 2)
+        };
+    }
+    total
+}
+
 fn neighbors(grid: &Grid, pos: Pos) -> Vec<Pos> {
     let mut result = Vec::new();
     if pos.0 > 0 && is_garden(grid, (pos.0 - 1, pos.1)) {
@@ -91,38 +298,6 @@ fn neighbors(grid: &Grid, pos: Pos) -> Vec<Pos> {
     result
 }
 
-fn ineighbors(grid: &Grid, pos: IPos) -> Vec<IPos> {
-    let mut result = Vec::new();
-    let rows = grid.len();
-    let cols = grid[0].len();
-    let north = if pos.1 .0 > 0 {
-        (pos.0, (pos.1 .0 - 1, pos.1 .1))
-    } else {
-        ((pos.0 .0 - 1, pos.0 .1), (rows - 1, pos.1 .1))
-    };
-    let south = if pos.1 .0 < rows - 1 {
-        (pos.0, (pos.1 .0 + 1, pos.1 .1))
-    } else {
-        ((pos.0 .0 + 1, pos.0 .1), (0, pos.1 .1))
-    };
-    let west = if pos.1 .1 > 0 {
-        (pos.0, (pos.1 .0, pos.1 .1 - 1))
-    } else {
-        ((pos.0 .0, pos.0 .1 - 1), (pos.1 .0, cols - 1))
-    };
-    let east = if pos.1 .1 < cols - 1 {
-        (pos.0, (pos.1 .0, pos.1 .1 + 1))
-    } else {
-        ((pos.0 .0, pos.0 .1 + 1), (pos.1 .0, 0))
-    };
-    for p in [north, south, east, west] {
-        if is_garden(grid, p.1) {
-            result.push(p);
-        }
-    }
-    result
-}
-
 fn is_garden(grid: &Grid, pos: Pos) -> bool {
     match grid[pos.0][pos.1] {
         Tile::Rock => false,
@@ -137,7 +312,6 @@ enum Tile {
 }
 
 type Pos = (usize, usize);
-type IPos = ((i32, i32), Pos);
 type Grid = Vec<Vec<Tile>>;
 
 fn parse(input: &str) -> (Grid, Pos) {
@@ -164,36 +338,84 @@ fn parse(input: &str) -> (Grid, Pos) {
     (g, start)
 }
 
-fn print_grid(grid: &Grid, pts: &HashSet<Pos>) {
-    for r in 0..grid.len() {
-        for c in 0..grid[0].len() {
-            if pts.contains(&(r, c)) {
-                print!("O");
-            } else if grid[r][c] == Tile::Rock {
-                print!("#");
-            } else {
-                print!(".");
+fn print_grid(grid: &Grid, dist: &Distances, steps: usize) {
+    for (r, row) in grid.iter().enumerate() {
+        for (c, tile) in row.iter().enumerate() {
+            match dist.dist[r][c] {
+                Some(d) if d <= steps && (steps - d).is_multiple_of(2) => print!("O"),
+                _ if *tile == Tile::Rock => print!("#"),
+                _ => print!("."),
             }
         }
-        println!("");
+        println!();
     }
 }
 
-fn iprint_grid(grid: &Grid, pts: &HashSet<IPos>) {
-    for rr in -1..=1 {
-        for r in 0..grid.len() {
-            for cc in -1..=1 {
-                for c in 0..grid[0].len() {
-                    if pts.contains(&((rr, cc), (r, c))) {
-                        print!("O");
-                    } else if grid[r][c] == Tile::Rock {
-                        print!("#");
-                    } else {
-                        print!(".");
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    const EXAMPLE: &str = "\
+...........
+.....###.#.
+.###.##..#.
+..#.#...#..
+....#.#....
+.##..S####.
+.##..#...#.
+.......##..
+.##.#.####.
+.##..##.##.
+...........";
+
+    /// Brute force on the infinite grid: steps the whole frontier one plot at a
+    /// time, sharing nothing with the distance maps.
+    fn isolve(grid: &Grid, start: Pos, steps: usize) -> usize {
+        let rows = grid.len() as i64;
+        let cols = grid[0].len() as i64;
+        let mut curr = HashSet::new();
+        curr.insert((start.0 as i64, start.1 as i64));
+        for _ in 1..=steps {
+            let mut next = HashSet::new();
+            for (r, c) in curr.into_iter() {
+                for (rr, cc) in [(r - 1, c), (r + 1, c), (r, c - 1), (r, c + 1)] {
+                    let pos = (rr.rem_euclid(rows) as usize, cc.rem_euclid(cols) as usize);
+                    if is_garden(grid, pos) {
+                        next.insert((rr, cc));
                     }
                 }
             }
-            println!("");
+            curr = next;
+        }
+        curr.len()
+    }
+
+    #[test]
+    fn example_matches_brute_force() {
+        let (grid, start) = parse(EXAMPLE);
+        let tiled = TiledDistances::covering(&grid, start, 100);
+        for (steps, expected) in [(6, 16), (10, 50), (50, 1594), (100, 6536)] {
+            assert_eq!(isolve(&grid, start, steps), expected, "steps = {steps}");
+            assert_eq!(tiled.reachable(steps), expected, "steps = {steps}");
+            assert_eq!(count_reachable(&grid, start, steps), Some(expected));
+        }
+    }
+
+    #[test]
+    fn input_matches_brute_force() {
+        let (grid, start) = parse(DATA);
+        let rows = grid.len();
+        // One tiled BFS answers every step count up to the one it covers.
+        let tiled = TiledDistances::covering(&grid, start, rows + rows / 2);
+        for steps in [0, 1, 6, 10, 50, 64, 100, rows, rows + rows / 2] {
+            let expected = isolve(&grid, start, steps);
+            assert_eq!(tiled.reachable(steps), expected, "steps = {steps}");
+            assert_eq!(
+                count_reachable(&grid, start, steps),
+                Some(expected),
+                "steps = {steps}"
+            );
         }
     }
 }
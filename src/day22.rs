@@ -1,4 +1,4 @@
-use std::collections::{HashMap, HashSet, VecDeque};
+use std::collections::{HashMap, HashSet};
 
 const DATA: &str = include_str!("day22.txt");
 
@@ -33,7 +33,6 @@ pub fn part2() -> Option<()> {
 #[derive(Debug, Clone, PartialEq, Eq)]
 struct Grid {
     range: Range,
-    grid: Vec<Vec<Vec<u32>>>,
     blocks: Blocks,
     supports: HashMap<u32, Vec<u32>>,
     supported: HashMap<u32, Vec<u32>>,
@@ -41,32 +40,64 @@ struct Grid {
 
 impl Grid {
     fn count_chain_reactions(&self) -> usize {
-        let mut result = 0;
-        for b in self.blocks.0.iter() {
-            result += self.chain_reactions_for(b.id);
+        self.fall_counts().iter().skip(1).sum()
+    }
+
+    /// How many other bricks fall if each brick is removed, indexed by id.
+    ///
+    /// With the ground as root of the support DAG, a brick falls exactly when
+    /// every path from the ground to it passes through the removed brick,
+    /// i.e. when the removed brick dominates it. So each brick's count is the
+    /// size of its subtree in the dominator tree, less itself.
+    fn fall_counts(&self) -> Vec<usize> {
+        let n = self.blocks.0.len() + 1;
+        let order = self.topological_order();
+
+        // In a DAG the immediate dominator of a node is the lowest common
+        // ancestor, in the dominator tree built so far, of all its supporters.
+        let levels = (usize::BITS - n.leading_zeros()) as usize;
+        let mut up = vec![vec![0u32; n]; levels.max(1)];
+        let mut depth = vec![0usize; n];
+        for &v in order.iter().skip(1) {
+            let mut below = self.supported[&v].iter().copied();
+            let first = below.next().unwrap();
+            let idom = below.fold(first, |a, b| lca(&up, &depth, a, b));
+            depth[v as usize] = depth[idom as usize] + 1;
+            up[0][v as usize] = idom;
+            for k in 1..up.len() {
+                up[k][v as usize] = up[k - 1][up[k - 1][v as usize] as usize];
+            }
+        }
+
+        let mut size = vec![1usize; n];
+        for &v in order.iter().skip(1).rev() {
+            size[up[0][v as usize] as usize] += size[v as usize];
         }
-        result
+        size.iter().map(|s| s - 1).collect()
     }
 
-    fn chain_reactions_for(&self, id: u32) -> usize {
-        let mut fallen = HashSet::new();
-        let mut gonzo = VecDeque::new();
-        gonzo.push_back(id);
-        fallen.insert(id);
-        while !gonzo.is_empty() {
-            let v = gonzo.pop_front().unwrap();
-            if let Some(mv) = self.supports.get(&v) {
-                for m in mv.iter() {
-                    let se = self.supported.get(m).unwrap();
-                    if se.iter().all(|v| fallen.contains(v)) {
-                        fallen.insert(*m);
-                        gonzo.push_back(*m);
+    /// Ground first, then every brick after all the bricks it rests on.
+    fn topological_order(&self) -> Vec<u32> {
+        let mut waiting = self
+            .supported
+            .iter()
+            .map(|(b, s)| (*b, s.len()))
+            .collect::<HashMap<_, _>>();
+        let mut order = vec![0];
+        let mut i = 0;
+        while i < order.len() {
+            if let Some(above) = self.supports.get(&order[i]) {
+                for b in above.iter() {
+                    let w = waiting.get_mut(b).unwrap();
+                    *w -= 1;
+                    if *w == 0 {
+                        order.push(*b);
                     }
                 }
             }
+            i += 1;
         }
-        // we subtract one because we don't count the initial block itself
-        fallen.len() - 1
+        order
     }
 
     fn count_disintegrated(&self) -> usize {
@@ -83,114 +114,248 @@ impl Grid {
     }
 
     fn new(blocks: Blocks, range: Range) -> Self {
-        let mut grid = Vec::new();
-        for _ in 0..=range.z[1] {
-            let mut r = Vec::new();
-            for _ in range.x[0]..=range.x[1] {
-                let mut c = Vec::new();
-                for _ in range.y[0]..=range.y[1] {
-                    c.push(0);
-                }
-                r.push(c);
-            }
-            grid.push(r);
-        }
-
-        for b in blocks.0.iter() {
-            for z in b.start.z..=b.end.z {
-                for x in b.start.x..=b.end.x {
-                    for y in b.start.y..=b.end.y {
-                        grid[z as usize][x as usize][y as usize] = b.id;
-                    }
-                }
-            }
-        }
         Grid {
             blocks,
             range,
-            grid,
             supports: HashMap::new(),
             supported: HashMap::new(),
         }
     }
 
+    /// The ground sits just below the lowest brick, or at 0 if that is higher.
+    fn floor(&self) -> i64 {
+        self.range.z[0].min(1) - 1
+    }
+
+    /// Drops the bricks lowest first, keeping the height of the top surface
+    /// and the brick forming it for each `(x, y)` column that has one.
     fn settle(&mut self) {
-        for x in self.range.x[0]..=self.range.x[1] {
-            for y in self.range.y[0]..=self.range.y[1] {
-                let b = self.grid[1][x as usize][y as usize];
-                if b != 0 && !self.supported.contains_key(&b) {
-                    self.supported.insert(b, vec![0]);
-                    let e = self.supports.entry(0).or_insert_with(|| Vec::new());
-                    e.push(b);
+        let floor = self.floor();
+        let mut top: HashMap<(i64, i64), (i64, u32)> = HashMap::new();
+        let mut order = (0..self.blocks.0.len()).collect::<Vec<_>>();
+        order.sort_by_key(|i| self.blocks.0[*i].start.z);
+        for i in order {
+            let b = &mut self.blocks.0[i];
+            let columns = (b.start.x..=b.end.x)
+                .flat_map(|x| (b.start.y..=b.end.y).map(move |y| (x, y)))
+                .collect::<Vec<_>>();
+            let rest = columns
+                .iter()
+                .map(|xy| top.get(xy).map_or(floor, |t| t.0))
+                .max()
+                .unwrap_or(floor);
+            let mut below = Vec::new();
+            for xy in columns.iter() {
+                let sb = match top.get(xy) {
+                    Some(&(h, sb)) if h == rest => sb,
+                    None if rest == floor => 0,
+                    _ => continue,
+                };
+                if !below.contains(&sb) {
+                    below.push(sb);
                 }
             }
+
+            b.end.z -= b.start.z - (rest + 1);
+            b.start.z = rest + 1;
+            for xy in columns {
+                top.insert(xy, (b.end.z, b.id));
+            }
+            for sb in below.iter() {
+                self.supports.entry(*sb).or_default().push(b.id);
+            }
+            self.supported.insert(b.id, below);
         }
-        for z in 2..=self.range.z[1] {
-            for x in self.range.x[0]..=self.range.x[1] {
-                for y in self.range.y[0]..=self.range.y[1] {
-                    let b = self.grid[z as usize][x as usize][y as usize];
-                    if b == 0 || self.supported.contains_key(&b) {
-                        continue;
-                    }
-                    let mut zz = z - 1;
-                    let bb = &self.blocks.0[b as usize - 1];
-                    while zz > 0 {
-                        for xx in bb.start.x..=bb.end.x {
-                            for yy in bb.start.y..=bb.end.y {
-                                let sb = self.grid[zz as usize][xx as usize][yy as usize];
-                                if sb != 0 {
-                                    let spe = self.supported.entry(b).or_insert_with(|| Vec::new());
-                                    if !spe.contains(&sb) {
-                                        spe.push(sb);
-                                    }
-                                    let se = self.supports.entry(sb).or_insert_with(|| Vec::new());
-                                    if !se.contains(&b) {
-                                        se.push(b);
-                                    }
-                                }
-                            }
-                        }
-                        if self.supported.contains_key(&b) {
-                            break;
-                        }
-                        let offset = 1 + bb.end.z - bb.start.z;
-                        for xx in bb.start.x..=bb.end.x {
-                            for yy in bb.start.y..=bb.end.y {
-                                self.grid[(zz + offset) as usize][xx as usize][yy as usize] = 0;
-                                self.grid[zz as usize][xx as usize][yy as usize] = b;
-                            }
-                        }
-                        zz -= 1;
-                    }
-                    if !self.supported.contains_key(&b) {
-                        let spe = self.supported.entry(b).or_insert_with(|| Vec::new());
-                        if !spe.contains(&0) {
-                            spe.push(0);
-                        }
-                        let se = self.supports.entry(0).or_insert_with(|| Vec::new());
-                        if !se.contains(&b) {
-                            se.push(b);
-                        }
-                    }
+    }
+}
+
+/// Lowest common ancestor via binary lifting over `up[k][v]`, the `2^k`-th
+/// ancestor of `v`.
+fn lca(up: &[Vec<u32>], depth: &[usize], a: u32, b: u32) -> u32 {
+    let (mut a, mut b) = if depth[a as usize] < depth[b as usize] {
+        (b, a)
+    } else {
+        (a, b)
+    };
+    let diff = depth[a as usize] - depth[b as usize];
+    for (k, level) in up.iter().enumerate() {
+        if diff >> k & 1 == 1 {
+            a = level[a as usize];
+        }
+    }
+    if a == b {
+        return a;
+    }
+    for level in up.iter().rev() {
+        if level[a as usize] != level[b as usize] {
+            a = level[a as usize];
+            b = level[b as usize];
+        }
+    }
+    up[0][a as usize]
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct BrickReport {
+    id: u32,
+    start: Pos,
+    end: Pos,
+    /// Bricks directly below, empty when resting on the ground.
+    rests_on: Vec<u32>,
+    /// Bricks directly above.
+    holds: Vec<u32>,
+    safe: bool,
+    falls: usize,
+}
+
+/// Largest width or height, in cells, that `side_view` will draw.
+const MAX_VIEW: i64 = 1000;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum View {
+    /// Looking along y, so x runs across the page.
+    Xz,
+    /// Looking along x, so y runs across the page.
+    Yz,
+}
+
+impl Grid {
+    fn report(&self) -> Vec<BrickReport> {
+        let falls = self.fall_counts();
+        self.blocks
+            .0
+            .iter()
+            .map(|b| {
+                let mut rests_on = self.supported.get(&b.id).cloned().unwrap_or_default();
+                rests_on.retain(|s| *s != 0);
+                let holds = self.supports.get(&b.id).cloned().unwrap_or_default();
+                let safe = holds
+                    .iter()
+                    .all(|h| self.supported.get(h).map_or(0, |s| s.len()) > 1);
+                BrickReport {
+                    id: b.id,
+                    start: b.start.clone(),
+                    end: b.end.clone(),
+                    rests_on,
+                    holds,
+                    safe,
+                    falls: falls[b.id as usize],
                 }
+            })
+            .collect()
+    }
+
+    /// A side-on projection of the settled stack in the style of the puzzle's
+    /// illustrations: each cell shows the brick's letter, `?` where several
+    /// bricks line up, and `.` where there are none. Stacks with more than 26
+    /// bricks use `#` for every brick. Errors if the picture would be wider
+    /// or taller than `MAX_VIEW` cells.
+    fn side_view(&self, view: View) -> Result<String, String> {
+        let (across, span) = match view {
+            View::Xz => ((|p: &Pos| p.x) as fn(&Pos) -> i64, self.range.x),
+            View::Yz => ((|p: &Pos| p.y) as fn(&Pos) -> i64, self.range.y),
+        };
+        let floor = self.floor();
+        let top = self.blocks.0.iter().map(|b| b.end.z).max().unwrap_or(floor);
+        if span[1] - span[0] >= MAX_VIEW || top - floor > MAX_VIEW {
+            return Err(format!(
+                "{view:?} view of {} x {} cells is too large to draw",
+                span[1] - span[0] + 1,
+                top - floor
+            ));
+        }
+        let width = (span[1] - span[0] + 1) as usize;
+        let mut cells = vec![vec![None; width]; (top - floor) as usize + 1];
+        for b in self.blocks.0.iter() {
+            let label = if self.blocks.0.len() <= 26 {
+                (b'A' + (b.id - 1) as u8) as char
+            } else {
+                '#'
+            };
+            let lo = (across(&b.start) - span[0]) as usize;
+            let hi = (across(&b.end) - span[0]) as usize;
+            for z in b.start.z..=b.end.z {
+                for cell in cells[(z - floor) as usize][lo..=hi].iter_mut() {
+                    *cell = match cell {
+                        None => Some(label),
+                        Some(_) => Some('?'),
+                    };
+                }
+            }
+        }
+
+        let mut out = String::new();
+        let axis = match view {
+            View::Xz => 'x',
+            View::Yz => 'y',
+        };
+        out.push_str(&format!("{:>w$}\n", axis, w = width / 2 + 1));
+        out.extend(
+            (span[0]..=span[1]).map(|a| char::from_digit(a.rem_euclid(10) as u32, 10).unwrap()),
+        );
+        out.push('\n');
+        let middle = floor + (top - floor + 1) / 2;
+        for z in (floor + 1..=top).rev() {
+            out.extend(cells[(z - floor) as usize].iter().map(|c| c.unwrap_or('.')));
+            out.push_str(&format!(" {z}"));
+            if z == middle {
+                out.push_str(" z");
             }
+            out.push('\n');
+        }
+        out.push_str(&"-".repeat(width));
+        out.push_str(&format!(" {floor}\n"));
+        Ok(out)
+    }
+}
+
+pub fn report() -> Option<()> {
+    let blocks = parse(DATA);
+    let mut range = Range::new();
+    for b in blocks.0.iter() {
+        range.update(b);
+    }
+    let mut grid = Grid::new(blocks, range);
+    grid.settle();
+
+    for r in grid.report() {
+        println!(
+            "{}: {},{},{}~{},{},{} rests on {:?} holds {:?} safe = {} falls = {}",
+            r.id,
+            r.start.x,
+            r.start.y,
+            r.start.z,
+            r.end.x,
+            r.end.y,
+            r.end.z,
+            r.rests_on,
+            r.holds,
+            r.safe,
+            r.falls
+        );
+    }
+    for view in [View::Xz, View::Yz] {
+        match grid.side_view(view) {
+            Ok(picture) => println!("{picture}"),
+            Err(e) => println!("{e}\n"),
         }
     }
+    Some(())
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 struct Range {
-    x: [u8; 2],
-    y: [u8; 2],
-    z: [u16; 2],
+    x: [i64; 2],
+    y: [i64; 2],
+    z: [i64; 2],
 }
 
 impl Range {
     fn new() -> Self {
         Range {
-            x: [std::u8::MAX, std::u8::MIN],
-            y: [std::u8::MAX, std::u8::MIN],
-            z: [std::u16::MAX, std::u16::MIN],
+            x: [i64::MAX, i64::MIN],
+            y: [i64::MAX, i64::MIN],
+            z: [i64::MAX, i64::MIN],
         }
     }
 
@@ -218,9 +383,9 @@ impl Range {
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 struct Pos {
-    x: u8,
-    y: u8,
-    z: u16,
+    x: i64,
+    y: i64,
+    z: i64,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -233,15 +398,11 @@ struct Block {
 #[derive(Debug, Clone, PartialEq, Eq)]
 struct Blocks(Vec<Block>);
 
-impl FromIterator<u16> for Pos {
-    fn from_iter<T: IntoIterator<Item = u16>>(iter: T) -> Self {
+impl FromIterator<i64> for Pos {
+    fn from_iter<T: IntoIterator<Item = i64>>(iter: T) -> Self {
         let mut i = iter.into_iter();
         let (x, y, z) = (i.next().unwrap(), i.next().unwrap(), i.next().unwrap());
-        Pos {
-            x: x as u8,
-            y: y as u8,
-            z,
-        }
+        Pos { x, y, z }
     }
 }
 
@@ -251,15 +412,100 @@ fn parse(input: &str) -> Blocks {
         let (start, end) = line.split_once("~").unwrap();
         let start = start
             .split(",")
-            .map(|v| v.parse::<u16>().unwrap())
+            .map(|v| v.parse::<i64>().unwrap())
             .collect::<Pos>();
         let end = end
             .split(",")
-            .map(|v| v.parse::<u16>().unwrap())
+            .map(|v| v.parse::<i64>().unwrap())
             .collect::<Pos>();
 
         let id = (blocks.len() + 1) as u32;
-        blocks.push(Block { id, start, end });
+        blocks.push(Block {
+            id,
+            start: Pos {
+                x: start.x.min(end.x),
+                y: start.y.min(end.y),
+                z: start.z.min(end.z),
+            },
+            end: Pos {
+                x: start.x.max(end.x),
+                y: start.y.max(end.y),
+                z: start.z.max(end.z),
+            },
+        });
     }
     Blocks(blocks)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::VecDeque;
+
+    const EXAMPLE: &str = "\
+1,0,1~1,2,1
+0,0,2~2,0,2
+0,2,3~2,2,3
+0,0,4~0,2,4
+2,0,5~2,2,5
+0,1,6~2,1,6
+1,1,8~1,1,9";
+
+    /// Brute force: knocks out one brick and follows the bricks left with
+    /// nothing under them.
+    fn chain_reactions_for(grid: &Grid, id: u32) -> usize {
+        let mut fallen = HashSet::new();
+        let mut gonzo = VecDeque::new();
+        gonzo.push_back(id);
+        fallen.insert(id);
+        while !gonzo.is_empty() {
+            let v = gonzo.pop_front().unwrap();
+            if let Some(mv) = grid.supports.get(&v) {
+                for m in mv.iter() {
+                    let se = grid.supported.get(m).unwrap();
+                    if se.iter().all(|v| fallen.contains(v)) {
+                        fallen.insert(*m);
+                        gonzo.push_back(*m);
+                    }
+                }
+            }
+        }
+        // we subtract one because we don't count the initial block itself
+        fallen.len() - 1
+    }
+
+    fn settled(input: &str) -> Grid {
+        let blocks = parse(input);
+        let mut range = Range::new();
+        for b in blocks.0.iter() {
+            range.update(b);
+        }
+        let mut grid = Grid::new(blocks, range);
+        grid.settle();
+        grid
+    }
+
+    fn assert_fall_counts(grid: &Grid) {
+        let counts = grid.fall_counts();
+        for b in grid.blocks.0.iter() {
+            assert_eq!(
+                counts[b.id as usize],
+                chain_reactions_for(grid, b.id),
+                "brick {}",
+                b.id
+            );
+        }
+    }
+
+    #[test]
+    fn example_fall_counts() {
+        let grid = settled(EXAMPLE);
+        assert_fall_counts(&grid);
+        assert_eq!(grid.count_chain_reactions(), 7);
+    }
+
+    #[test]
+    fn input_fall_counts() {
+        assert_fall_counts(&settled(DATA));
+    }
+}
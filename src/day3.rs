@@ -1,157 +1,164 @@
 const DATA: &str = include_str!("day3.txt");
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeSet, HashMap};
 
-enum Elem {
-    Num(usize, u32),
-    Symbol(char),
+type Pos = (usize, usize);
+
+pub fn part1() -> Option<()> {
+    let schematic = Schematic::parse(DATA)?;
+    let result: u64 = schematic.part_numbers().map(|n| n.value).sum();
+    println!("result = {result}");
+    Some(())
+}
+
+pub fn part2() -> Option<()> {
+    let schematic = Schematic::parse(DATA)?;
+    let result: u64 = schematic
+        .gears('*', 2)
+        .map(|(_, nums)| nums.iter().map(|n| n.value).product::<u64>())
+        .sum();
+    println!("result = {result}");
+    Some(())
 }
 
-enum State {
-    None,
-    Num(usize, u32),
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Number {
+    row: usize,
+    /// Columns `start..end`.
+    start: usize,
+    end: usize,
+    value: u64,
 }
 
-pub fn part1() -> Option<()> {
-    let mut grid = HashMap::new();
-    let mut symbols = HashSet::new();
-    let mut row = 0;
-    let mut state = State::None;
-    let mut cols = 0;
-    let mut id = 0;
-    for line in DATA.lines() {
-        state = State::None;
-        for (col, c) in line.chars().enumerate() {
-            cols = std::cmp::max(col, cols);
-            if c.is_digit(10) {
-                state = match state {
-                    State::None => State::Num(col, c.to_digit(10)?),
-                    State::Num(start, acc) => State::Num(start, acc * 10 + c.to_digit(10)?),
-                };
-            } else {
-                if let State::Num(start, v) = state {
-                    state = State::None;
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+struct Symbol {
+    pos: Pos,
+    kind: char,
+}
+
+/// Every number span and symbol in a schematic, with a lookup from each
+/// cell a number covers back to that number.
+struct Schematic {
+    numbers: Vec<Number>,
+    symbols: Vec<Symbol>,
+    cells: HashMap<Pos, usize>,
+}
+
+impl Schematic {
+    fn parse(input: &str) -> Option<Self> {
+        let mut schematic = Schematic {
+            numbers: Vec::new(),
+            symbols: Vec::new(),
+            cells: HashMap::new(),
+        };
+        for (row, line) in input.lines().enumerate() {
+            let chars = line.chars().collect::<Vec<_>>();
+            let mut col = 0;
+            while col < chars.len() {
+                let c = chars[col];
+                if c.is_ascii_digit() {
+                    let start = col;
+                    while col < chars.len() && chars[col].is_ascii_digit() {
+                        col += 1;
+                    }
+                    let value = chars[start..col]
+                        .iter()
+                        .collect::<String>()
+                        .parse::<u64>()
+                        .ok()?;
                     for cc in start..col {
-                        grid.insert((row, cc), Elem::Num(id, v));
+                        schematic.cells.insert((row, cc), schematic.numbers.len());
                     }
-                    id += 1;
+                    schematic.numbers.push(Number {
+                        row,
+                        start,
+                        end: col,
+                        value,
+                    });
+                    continue;
                 }
                 if c != '.' {
-                    grid.insert((row, col), Elem::Symbol(c));
-                    symbols.insert((row, col));
+                    schematic.symbols.push(Symbol {
+                        pos: (row, col),
+                        kind: c,
+                    });
                 }
+                col += 1;
             }
         }
-        if let State::Num(start, v) = state {
-            state = State::None;
-            for cc in start..=cols {
-                grid.insert((row, cc), Elem::Num(id, v));
-            }
-            id += 1;
-        }
-        row += 1;
-    }
-    if let State::Num(start, v) = state {
-        for cc in start..=cols {
-            grid.insert((row, cc), Elem::Num(id, v));
-        }
+        Some(schematic)
     }
 
-    let mut parts = Vec::new();
-    for (r, c) in symbols.into_iter() {
-        let mut seen = HashSet::new();
-        for r_offset in -1..2 {
-            for c_offset in -1..2 {
-                let rr = r as i32 + r_offset;
-                let cc = c as i32 + c_offset;
-                if rr >= 0 && cc >= 0 {
-                    if let Some(Elem::Num(id, v)) = grid.get(&(rr as usize, cc as usize)) {
-                        if seen.insert(*id) {
-                            parts.push(*v)
-                        }
-                    }
+    /// Indices of the distinct numbers touching `pos`, diagonals included.
+    fn neighbors(&self, (r, c): Pos) -> BTreeSet<usize> {
+        let mut found = BTreeSet::new();
+        for rr in r.saturating_sub(1)..=r + 1 {
+            for cc in c.saturating_sub(1)..=c + 1 {
+                if let Some(id) = self.cells.get(&(rr, cc)) {
+                    found.insert(*id);
                 }
             }
         }
+        found
     }
 
-    let result: u32 = parts.iter().sum();
-    println!("result = {result}");
-
-    Some(())
-}
-
-pub fn part2() -> Option<()> {
-    let mut grid = HashMap::new();
-    let mut symbols = HashSet::new();
-    let mut row = 0;
-    let mut state = State::None;
-    let mut cols = 0;
-    let mut id = 0;
-    for line in DATA.lines() {
-        state = State::None;
-        for (col, c) in line.chars().enumerate() {
-            cols = std::cmp::max(col, cols);
-            if c.is_digit(10) {
-                state = match state {
-                    State::None => State::Num(col, c.to_digit(10)?),
-                    State::Num(start, acc) => State::Num(start, acc * 10 + c.to_digit(10)?),
-                };
-            } else {
-                if let State::Num(start, v) = state {
-                    state = State::None;
-                    for cc in start..col {
-                        grid.insert((row, cc), Elem::Num(id, v));
-                    }
-                    id += 1;
-                }
-                if c != '.' {
-                    grid.insert((row, col), Elem::Symbol(c));
-                    symbols.insert((row, col, c));
-                }
-            }
-        }
-        if let State::Num(start, v) = state {
-            state = State::None;
-            for cc in start..=cols {
-                grid.insert((row, cc), Elem::Num(id, v));
-            }
-            id += 1;
-        }
-        row += 1;
+    /// Numbers touching at least one symbol accepted by `keep`, each once.
+    fn adjacent_where(&self, keep: impl Fn(char) -> bool) -> impl Iterator<Item = &Number> {
+        self.symbols
+            .iter()
+            .filter(|s| keep(s.kind))
+            .flat_map(|s| self.neighbors(s.pos))
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .map(|id| &self.numbers[id])
     }
-    if let State::Num(start, v) = state {
-        for cc in start..=cols {
-            grid.insert((row, cc), Elem::Num(id, v));
-        }
+
+    fn part_numbers(&self) -> impl Iterator<Item = &Number> {
+        self.adjacent_where(|_| true)
     }
 
-    let mut ratios = Vec::new();
-    for (r, c, sym) in symbols.into_iter() {
-        if sym != '*' {
-            continue;
-        }
-        let mut nums = Vec::new();
-        let mut seen = HashSet::new();
-        for r_offset in -1..2 {
-            for c_offset in -1..2 {
-                let rr = r as i32 + r_offset;
-                let cc = c as i32 + c_offset;
-                if rr >= 0 && cc >= 0 {
-                    if let Some(Elem::Num(id, v)) = grid.get(&(rr as usize, cc as usize)) {
-                        if seen.insert(*id) {
-                            nums.push(*v)
-                        }
-                    }
-                }
-            }
-        }
-        if nums.len() == 2 {
-            ratios.push(nums[0] * nums[1]);
-        }
+    fn adjacent_to(&self, kind: char) -> impl Iterator<Item = &Number> {
+        self.adjacent_where(move |k| k == kind)
     }
 
-    let result: u32 = ratios.iter().sum();
-    println!("result = {result}");
+    /// Symbols of `kind` touching exactly `count` numbers, with those numbers.
+    fn gears(&self, kind: char, count: usize) -> impl Iterator<Item = (Pos, Vec<&Number>)> {
+        self.symbols
+            .iter()
+            .filter(move |s| s.kind == kind)
+            .map(|s| {
+                let nums = self
+                    .neighbors(s.pos)
+                    .into_iter()
+                    .map(|id| &self.numbers[id]);
+                (s.pos, nums.collect::<Vec<_>>())
+            })
+            .filter(move |(_, nums)| nums.len() == count)
+    }
+}
 
+/// Prints the numbers touching each kind of symbol, and those touching none.
+pub fn report() -> Option<()> {
+    let schematic = Schematic::parse(DATA)?;
+    let kinds = schematic
+        .symbols
+        .iter()
+        .map(|s| s.kind)
+        .collect::<BTreeSet<_>>();
+    for kind in kinds {
+        let nums = schematic.adjacent_to(kind).collect::<Vec<_>>();
+        println!(
+            "{kind}: {} symbols, {} numbers, sum {}",
+            schematic.symbols.iter().filter(|s| s.kind == kind).count(),
+            nums.len(),
+            nums.iter().map(|n| n.value).sum::<u64>()
+        );
+    }
+    let parts = schematic.part_numbers().collect::<Vec<_>>();
+    for n in schematic.numbers.iter().filter(|n| !parts.contains(n)) {
+        println!(
+            "unattached: {} at row {} cols {}..{}",
+            n.value, n.row, n.start, n.end
+        );
+    }
     Some(())
 }
@@ -2,60 +2,122 @@ const DATA: &str = include_str!("day4.txt");
 use std::collections::HashSet;
 
 pub fn part1() -> Option<()> {
-    let mut result: u32 = 0;
-    for line in DATA.lines() {
-        let (win, card) = line.split_once(" | ")?;
-        let win_nums = win
-            .split_once(": ")?
-            .1
-            .split_ascii_whitespace()
-            .collect::<HashSet<_>>();
-        let card_nums = card.split_ascii_whitespace().collect::<Vec<_>>();
-        let mut winning_count = 0;
-        for num in card_nums {
-            if win_nums.contains(num) {
-                winning_count += 1;
-            }
-        }
-        if winning_count > 0 {
-            result += 2_u32.pow(winning_count - 1);
-        }
-    }
+    let result = total_points(DATA).map_err(|e| eprintln!("{e}")).ok()?;
     println!("result = {result}");
     Some(())
 }
 
 pub fn part2() -> Option<()> {
-    let mut cards = Vec::new();
-    let mut card_counts = Vec::new();
-    for line in DATA.lines() {
-        let (win, card) = line.split_once(" | ")?;
-        let (_, win_data) = win.split_once(": ")?;
-        let win_nums = win_data.split_ascii_whitespace().collect::<HashSet<_>>();
-        let card_nums = card.split_ascii_whitespace().collect::<Vec<_>>();
-        let mut winning_count = 0;
-        for num in card_nums {
-            if win_nums.contains(num) {
-                winning_count += 1;
-            }
-        }
-        if winning_count > 0 {
-            cards.push(winning_count);
-        } else {
-            cards.push(0);
+    let result = copies(DATA)
+        .map_err(|e| eprintln!("{e}"))
+        .ok()?
+        .iter()
+        .try_fold(0u64, |acc, n| acc.checked_add(*n));
+    let Some(result) = result else {
+        eprintln!("total number of cards overflows u64");
+        return None;
+    };
+    println!("result = {result}");
+    Some(())
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Card {
+    id: usize,
+    winning: HashSet<u32>,
+    have: Vec<u32>,
+}
+
+impl Card {
+    fn parse(line: &str) -> Result<Self, String> {
+        let (label, numbers) = line
+            .split_once(':')
+            .ok_or_else(|| format!("expected \"Card N:\" in {line:?}"))?;
+        let id = label
+            .strip_prefix("Card")
+            .and_then(|id| id.trim().parse::<usize>().ok())
+            .ok_or_else(|| format!("bad card label {label:?}"))?;
+        let (winning, have) = numbers
+            .split_once('|')
+            .ok_or_else(|| format!("expected '|' in card {id}"))?;
+        let nums = |s: &str| {
+            s.split_ascii_whitespace()
+                .map(|n| {
+                    n.parse::<u32>()
+                        .map_err(|_| format!("bad number {n:?} in card {id}"))
+                })
+                .collect::<Result<Vec<_>, _>>()
+        };
+        Ok(Card {
+            id,
+            winning: nums(winning)?.into_iter().collect(),
+            have: nums(have)?,
+        })
+    }
+
+    fn matches(&self) -> usize {
+        self.have
+            .iter()
+            .filter(|n| self.winning.contains(n))
+            .count()
+    }
+
+    /// 1 point for the first match, doubled for every match after it.
+    fn points(&self) -> Result<u64, String> {
+        match self.matches() {
+            0 => Ok(0),
+            m => 1u64
+                .checked_shl(m as u32 - 1)
+                .ok_or_else(|| format!("{m} matches on card {} overflow u64", self.id)),
         }
-        card_counts.push(1);
     }
-    for i in 0..cards.len() {
-        let mult = cards[i] as usize;
-        let prev_mult = card_counts[i];
-        for j in 1..=mult {
-            if i + j < cards.len() {
-                card_counts[i + j] += prev_mult;
-            }
+}
+
+fn parse(input: &str) -> Result<Vec<Card>, String> {
+    input.lines().map(Card::parse).collect()
+}
+
+fn total_points(input: &str) -> Result<u64, String> {
+    parse(input)?.iter().try_fold(0u64, |acc, card| {
+        acc.checked_add(card.points()?)
+            .ok_or_else(|| "total points overflow u64".to_string())
+    })
+}
+
+/// How many copies of each card end up held, originals included. Each card
+/// wins one copy of the next `matches` cards per copy of itself, and cards
+/// past the end of the table are never won.
+fn copies(input: &str) -> Result<Vec<u64>, String> {
+    let cards = parse(input)?;
+    let mut counts = vec![1u64; cards.len()];
+    for (i, card) in cards.iter().enumerate() {
+        let end = (i + 1 + card.matches()).min(cards.len());
+        for j in i + 1..end {
+            counts[j] = counts[j]
+                .checked_add(counts[i])
+                .ok_or_else(|| format!("copies of card {} overflow u64", cards[j].id))?;
         }
     }
-    let result: u32 = card_counts.iter().sum();
-    println!("result = {result}");
+    Ok(counts)
+}
+
+/// Prints each card's matches, points and copies held once the cascade of
+/// won cards has finished.
+pub fn report() -> Option<()> {
+    let cards = parse(DATA).map_err(|e| eprintln!("{e}")).ok()?;
+    let counts = copies(DATA).map_err(|e| eprintln!("{e}")).ok()?;
+    println!(
+        "{:>6} {:>8} {:>10} {:>16}",
+        "card", "matches", "points", "copies"
+    );
+    for (card, count) in cards.iter().zip(counts) {
+        println!(
+            "{:>6} {:>8} {:>10} {:>16}",
+            card.id,
+            card.matches(),
+            card.points().map_err(|e| eprintln!("{e}")).ok()?,
+            count
+        );
+    }
     Some(())
 }
@@ -1,4 +1,7 @@
 const DATA: &str = include_str!("day5.txt");
+const SEED_CHUNK: usize = 1 << 20;
+
+use crate::parallel;
 
 #[derive(Debug)]
 struct Range {
@@ -149,12 +152,21 @@ pub fn part2() -> Option<()> {
         });
     }
 
-    let mut result = std::usize::MAX;
-    for sr in seeds.iter() {
-        for s in sr.start..sr.start + sr.len {
-            result = std::cmp::min(result, map_through_range(s, &maps));
-        }
-    }
+    let chunks = seeds
+        .iter()
+        .flat_map(|sr| sr.chunks(SEED_CHUNK))
+        .collect::<Vec<_>>();
+    let result = parallel::map_reduce(
+        &chunks,
+        |sr| {
+            (sr.start..sr.start + sr.len)
+                .map(|s| map_through_range(s, &maps))
+                .min()
+                .unwrap_or(usize::MAX)
+        },
+        usize::MAX,
+        std::cmp::min,
+    );
     println!("result = {result}");
     Some(())
 }
@@ -171,6 +183,16 @@ fn map_through_range(seed: usize, maps: &Vec<Vec<Range>>) -> usize {
 }
 
 impl SeedRange {
+    fn chunks(&self, size: usize) -> Vec<SeedRange> {
+        (self.start..self.start + self.len)
+            .step_by(size)
+            .map(|start| SeedRange {
+                start,
+                len: std::cmp::min(size, self.start + self.len - start),
+            })
+            .collect()
+    }
+
     fn overlap(&self, other: &SeedRange) -> Option<usize> {
         if self.start < other.start + other.len && self.start + self.len > other.start {
             let start = if self.start <= other.start {
@@ -1,74 +1,182 @@
 const DATA: &str = include_str!("day6.txt");
+use std::ops::RangeInclusive;
 
 pub fn part1() -> Option<()> {
-    let mut data = DATA.lines();
-    let times = data
-        .next()?
-        .split_once(": ")?
-        .1
-        .split_whitespace()
-        .map(|x| x.trim().parse::<u32>().unwrap())
-        .collect::<Vec<_>>();
-
-    let dists = data
-        .next()?
-        .split_once(": ")?
-        .1
-        .split_whitespace()
-        .map(|x| x.trim().parse::<u32>().unwrap())
-        .collect::<Vec<_>>();
-
-    let races = times.into_iter().zip(dists.into_iter()).collect::<Vec<_>>();
-
+    let races = parse(DATA, false).map_err(|e| eprintln!("{e}")).ok()?;
     let mut result = 1;
-    for (t, d) in races.into_iter() {
-        let mut count = 0;
-        for n in 0..=t {
-            if n * (t - n) > d {
-                count += 1;
-            }
-        }
-        result *= count;
+    for race in races.iter() {
+        result *= race.ways_to_win();
     }
     println!("result = {result}");
-
     Some(())
 }
 
 pub fn part2() -> Option<()> {
-    let mut data = DATA.lines();
-    let times = data
-        .next()?
-        .split_once(": ")?
-        .1
-        .split_whitespace()
-        .map(|x| x.trim())
-        .collect::<Vec<_>>();
+    let races = parse(DATA, true).map_err(|e| eprintln!("{e}")).ok()?;
+    let result = races.first()?.ways_to_win();
+    println!("result = {result}");
+    Some(())
+}
 
-    let time = times.join("").parse::<usize>().ok()?;
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+struct Race {
+    time: u128,
+    dist: u128,
+}
 
-    let dists = data
-        .next()?
-        .split_once(": ")?
-        .1
-        .split_whitespace()
-        .map(|x| x.trim())
-        .collect::<Vec<_>>();
+/// Reads the time and distance rows. With `joined` each row is a single
+/// race whose digits are split up by spaces.
+fn parse(input: &str, joined: bool) -> Result<Vec<Race>, String> {
+    let mut lines = input.lines();
+    let mut row = |name: &str| -> Result<Vec<u128>, String> {
+        let line = lines.next().ok_or_else(|| format!("missing {name} row"))?;
+        let values = line
+            .strip_prefix(name)
+            .and_then(|l| l.strip_prefix(':'))
+            .ok_or_else(|| format!("expected {name:?} row, found {line:?}"))?;
+        let words = if joined {
+            vec![values.split_whitespace().collect::<String>()]
+        } else {
+            values.split_whitespace().map(String::from).collect()
+        };
+        words
+            .iter()
+            .map(|w| w.parse::<u128>().map_err(|_| format!("bad {name} {w:?}")))
+            .collect()
+    };
+    let times = row("Time")?;
+    let dists = row("Distance")?;
+    if times.len() != dists.len() {
+        return Err(format!(
+            "{} times but {} distances",
+            times.len(),
+            dists.len()
+        ));
+    }
+    Ok(times
+        .into_iter()
+        .zip(dists)
+        .map(|(time, dist)| Race { time, dist })
+        .collect())
+}
 
-    let dist = dists.join("").parse::<usize>().ok()?;
+impl Race {
+    /// Whether holding the button for `hold` beats the record. Overflowing
+    /// the product can only mean a distance far beyond any `u128` record.
+    fn beats(&self, hold: u128) -> bool {
+        hold.checked_mul(self.time - hold)
+            .is_none_or(|d| d > self.dist)
+    }
 
-    let result = compute(time, dist);
-    println!("result {result}");
+    /// Every hold time that beats the record: isqrt of the discriminant, then
+    /// step to the exact boundary; binary search when `time^2` overflows.
+    fn winning_holds(&self) -> Option<RangeInclusive<u128>> {
+        let (t, d) = (self.time, self.dist);
+        let mid = t / 2;
+        if !self.beats(mid) {
+            return None;
+        }
+        let low = match t.checked_mul(t).zip(d.checked_mul(4)) {
+            Some((square, record)) => {
+                let mut low = (t - (square - record).isqrt()) / 2;
+                while low > 0 && self.beats(low - 1) {
+                    low -= 1;
+                }
+                while !self.beats(low) {
+                    low += 1;
+                }
+                low
+            }
+            None => {
+                let (mut lo, mut hi) = (0, mid);
+                while lo < hi {
+                    let m = lo + (hi - lo) / 2;
+                    if self.beats(m) {
+                        hi = m;
+                    } else {
+                        lo = m + 1;
+                    }
+                }
+                lo
+            }
+        };
+        Some(low..=t - low)
+    }
 
+    fn ways_to_win(&self) -> u128 {
+        self.winning_holds().map_or(0, |r| r.end() - r.start() + 1)
+    }
+}
+
+/// Prints the range of winning hold times for every race.
+pub fn print_holds(joined: bool) -> Option<()> {
+    let races = parse(DATA, joined).map_err(|e| eprintln!("{e}")).ok()?;
+    for race in races.iter() {
+        match race.winning_holds() {
+            Some(r) => println!(
+                "time {} record {}: hold {}..={} ({} ways)",
+                race.time,
+                race.dist,
+                r.start(),
+                r.end(),
+                r.end() - r.start() + 1
+            ),
+            None => println!("time {} record {}: no way to win", race.time, race.dist),
+        }
+    }
     Some(())
 }
 
-fn compute(time: usize, dist: usize) -> usize {
-    let det = time * time - 4 * dist;
-    let fac = (det as f64).sqrt() / 2_f64;
-    let fixed = time as f64 / 2_f64;
-    let low_n = (fixed - fac).ceil() as usize;
-    let high_n = (fixed + fac).floor() as usize;
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn holds(time: u128, dist: u128) -> Option<RangeInclusive<u128>> {
+        Race { time, dist }.winning_holds()
+    }
+
+    #[test]
+    fn example() {
+        let input = "Time:      7  15   30\nDistance:  9  40  200\n";
+        let races = parse(input, false).unwrap();
+        let ways = races.iter().map(Race::ways_to_win).collect::<Vec<_>>();
+        assert_eq!(ways, [4, 8, 9]);
+        assert_eq!(parse(input, true).unwrap()[0].ways_to_win(), 71503);
+    }
+
+    #[test]
+    fn ties_at_the_roots() {
+        assert_eq!(holds(30, 200), Some(11..=19));
+        assert_eq!(holds(4, 4), None);
+        assert_eq!(holds(4, 3), Some(2..=2));
+        assert_eq!(holds(0, 0), None);
+    }
+
+    #[test]
+    fn matches_brute_force() {
+        for time in 0..60u128 {
+            for dist in 0..=time * time / 4 + 1 {
+                let winners = (0..=time)
+                    .filter(|h| h * (time - h) > dist)
+                    .collect::<Vec<_>>();
+                let expected = winners.first().map(|lo| *lo..=*winners.last().unwrap());
+                assert_eq!(holds(time, dist), expected, "time {time} dist {dist}");
+            }
+        }
+    }
 
-    high_n - low_n + 1
+    #[test]
+    fn large_times() {
+        let time = 1u128 << 70;
+        assert_eq!(holds(time, 0), Some(1..=time - 1));
+        // Holding for exactly 2^50 only ties the record.
+        let k = 1u128 << 50;
+        assert_eq!(holds(time, k * (time - k)), Some(k + 1..=time - k - 1));
+        assert_eq!(holds(u128::MAX, u128::MAX), Some(2..=u128::MAX - 2));
+        let k = 1u128 << 60;
+        assert_eq!(
+            holds(1 << 64, k * ((1 << 64) - k)),
+            Some(k + 1..=(1 << 64) - k - 1)
+        );
+    }
 }
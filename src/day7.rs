@@ -1,69 +1,109 @@
 const DATA: &str = include_str!("day7.txt");
-use std::{cmp::Ordering, collections::HashMap};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::{cmp::Ordering, fs, path::Path};
 
 pub fn part1() -> Option<()> {
-    let mut cards = Vec::new();
-    for line in DATA.lines() {
-        let (a, b) = line.split_once(" ")?;
-        cards.push((a, b.parse::<usize>().ok()?));
-    }
-    cards.sort_by(|a, b| compare(a.0, b.0));
-    let mut result = 0;
-    for (i, (_, b)) in cards.into_iter().enumerate() {
-        result += (i + 1) * b;
-    }
+    let result = winnings(&Rules::standard(), DATA)?;
     println!("result = {result}");
     Some(())
 }
 
 pub fn part2() -> Option<()> {
+    let result = winnings(&Rules::jokers(), DATA)?;
+    println!("result = {result}");
+    Some(())
+}
+
+fn winnings(rules: &Rules, input: &str) -> Option<usize> {
+    Some(rank_hands(rules, input)?.iter().map(|h| h.winnings).sum())
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct HandReport<'a> {
+    hand: &'a str,
+    bid: usize,
+    kind: Kind,
+    /// The hand with every wildcard replaced by the card it plays as.
+    best: String,
+    rank: usize,
+    winnings: usize,
+}
+
+/// Every hand in rank order, weakest first.
+fn rank_hands<'a>(rules: &Rules, input: &'a str) -> Option<Vec<HandReport<'a>>> {
     let mut cards = Vec::new();
-    for line in DATA.lines() {
+    for line in input.lines() {
         let (a, b) = line.split_once(" ")?;
+        if !rules.is_valid(a) {
+            return None;
+        }
         cards.push((a, b.parse::<usize>().ok()?));
     }
-    cards.sort_by(|a, b| joker_compare(a.0, b.0));
-    let mut result = 0;
-    for (i, (_, b)) in cards.into_iter().enumerate() {
-        result += (i + 1) * b;
+    cards.sort_by(|a, b| rules.compare(a.0, b.0));
+    let hands = cards
+        .into_iter()
+        .enumerate()
+        .map(|(i, (hand, bid))| HandReport {
+            hand,
+            bid,
+            kind: rules.kind(hand),
+            best: rules.substitute(hand),
+            rank: i + 1,
+            winnings: (i + 1) * bid,
+        })
+        .collect();
+    Some(hands)
+}
+
+fn kind_counts(hands: &[HandReport]) -> BTreeMap<Kind, usize> {
+    let mut counts = BTreeMap::new();
+    for h in hands.iter() {
+        *counts.entry(h.kind).or_insert(0) += 1;
     }
-    println!("result = {result}");
-    Some(())
+    counts
 }
 
-fn joker_compare(a: &str, b: &str) -> std::cmp::Ordering {
-    let ak = joker_hand_kind(a);
-    let bk = joker_hand_kind(b);
-
-    let ck = ak.cmp(&bk);
-    if ck == std::cmp::Ordering::Equal {
-        for (aa, bb) in a.chars().zip(b.chars()) {
-            let cc = joker_compare_card(aa, bb);
-            if cc != Ordering::Equal {
-                return cc;
-            }
-        }
+fn to_csv(hands: &[HandReport]) -> String {
+    let mut out = String::from("hand,bid,kind,best,rank,winnings\n");
+    for h in hands.iter() {
+        out.push_str(&format!(
+            "{},{},{:?},{},{},{}\n",
+            h.hand, h.bid, h.kind, h.best, h.rank, h.winnings
+        ));
     }
-    return ck;
+    out
 }
 
-fn compare(a: &str, b: &str) -> std::cmp::Ordering {
-    let ak = hand_kind(a);
-    let bk = hand_kind(b);
-
-    let ck = ak.cmp(&bk);
-    if ck == std::cmp::Ordering::Equal {
-        for (aa, bb) in a.chars().zip(b.chars()) {
-            let cc = compare_card(aa, bb);
-            if cc != Ordering::Equal {
-                return cc;
-            }
-        }
+/// Prints how many hands make each kind with and without jokers, and writes
+/// the joker ranking as CSV to `csv`, or stdout without one.
+pub fn report(csv: Option<&Path>) -> Option<()> {
+    let plain = rank_hands(&Rules::standard(), DATA)?;
+    let jokers = rank_hands(&Rules::jokers(), DATA)?;
+    let plain_counts = kind_counts(&plain);
+    let joker_counts = kind_counts(&jokers);
+    println!("{:<8} {:>8} {:>8}", "kind", "plain", "jokers");
+    for kind in plain_counts
+        .keys()
+        .chain(joker_counts.keys())
+        .collect::<BTreeSet<_>>()
+    {
+        println!(
+            "{:<8} {:>8} {:>8}",
+            format!("{kind:?}"),
+            plain_counts.get(kind).unwrap_or(&0),
+            joker_counts.get(kind).unwrap_or(&0)
+        );
+    }
+    match csv {
+        Some(path) => fs::write(path, to_csv(&jokers))
+            .map_err(|e| eprintln!("cannot write {}: {e}", path.display()))
+            .ok()?,
+        None => print!("{}", to_csv(&jokers)),
     }
-    return ck;
+    Some(())
 }
 
-#[derive(Eq, PartialEq, PartialOrd, Ord, Debug)]
+#[derive(Copy, Clone, Eq, PartialEq, PartialOrd, Ord, Hash, Debug)]
 enum Kind {
     High,
     Pair,
@@ -74,89 +114,95 @@ enum Kind {
     Five,
 }
 
-fn hand_kind(a: &str) -> Kind {
-    let mut cards = HashMap::new();
-    for c in a.chars() {
-        let e = cards.entry(c).or_insert(0);
-        *e += 1;
-    }
-    match cards.len() {
-        1 => Kind::Five,
-        5 => Kind::High,
-        2 => cards
-            .into_values()
-            .find(|x| *x == 4)
-            .map_or(Kind::Full, |_| Kind::Four),
-        3 => cards
-            .into_values()
-            .find(|x| *x == 3)
-            .map_or(Kind::TwoPair, |_| Kind::Three),
-        _ => Kind::Pair,
-    }
+/// A Camel Cards variant: which cards exist and how they rank, which of them
+/// are wild, and how many make a hand.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Rules {
+    /// Every card, weakest first.
+    order: Vec<char>,
+    /// Cards that stand in for whatever makes the hand strongest.
+    wild: Vec<char>,
+    hand_size: usize,
 }
 
-fn joker_hand_kind(a: &str) -> Kind {
-    if !a.contains('J') {
-        return hand_kind(a);
+impl Rules {
+    fn new(order: &str, wild: &str, hand_size: usize) -> Self {
+        Rules {
+            order: order.chars().collect(),
+            wild: wild.chars().collect(),
+            hand_size,
+        }
     }
 
-    let mut cards = HashMap::new();
-    for c in a.chars() {
-        let e = cards.entry(c).or_insert(0);
-        *e += 1;
+    fn standard() -> Self {
+        Rules::new("23456789TJQKA", "", 5)
     }
-    let joker_count = cards.remove(&'J').unwrap();
-
-    match (cards.len(), joker_count) {
-        (_, 5) => Kind::Five,
-        (4, 1) => Kind::Pair,
-        (3, 1) => Kind::Three,
-        (3, 2) => Kind::Three,
-        (2, 1) => cards
-            .into_values()
-            .find(|x| *x == 2)
-            .map_or(Kind::Four, |_| Kind::Full),
-        (2, 2) => Kind::Four,
-        (2, 3) => Kind::Four,
-        (1, _) => Kind::Five,
-        _ => panic!("bad stuff: {:?}", cards),
+
+    fn jokers() -> Self {
+        Rules::new("J23456789TQKA", "J", 5)
     }
-}
 
-fn compare_card(a: char, b: char) -> Ordering {
-    if a == b {
-        return Ordering::Equal;
+    fn is_valid(&self, hand: &str) -> bool {
+        hand.chars().count() == self.hand_size && hand.chars().all(|c| self.order.contains(&c))
     }
-    match (a, b) {
-        ('A', _) => Ordering::Greater,
-        (_, 'A') => Ordering::Less,
-        ('K', _) => Ordering::Greater,
-        (_, 'K') => Ordering::Less,
-        ('Q', _) => Ordering::Greater,
-        (_, 'Q') => Ordering::Less,
-        ('J', _) => Ordering::Greater,
-        (_, 'J') => Ordering::Less,
-        ('T', _) => Ordering::Greater,
-        (_, 'T') => Ordering::Less,
-        _ => a.cmp(&b),
+
+    fn strength(&self, card: char) -> Option<usize> {
+        self.order.iter().position(|c| *c == card)
     }
-}
 
-fn joker_compare_card(a: char, b: char) -> Ordering {
-    if a == b {
-        return Ordering::Equal;
+    /// The hand with each wildcard turned into the card that makes it
+    /// strongest. Wildcards always do best joining the largest group of real
+    /// cards, whatever shape the rest of the hand has; ties and all-wild
+    /// hands go to the highest ranked card.
+    fn substitute(&self, hand: &str) -> String {
+        let mut cards = HashMap::new();
+        for c in hand.chars().filter(|c| !self.wild.contains(c)) {
+            *cards.entry(c).or_insert(0) += 1;
+        }
+        let target = cards
+            .into_iter()
+            .max_by_key(|(c, n)| (*n, self.strength(*c)))
+            .map(|(c, _)| c)
+            .or_else(|| {
+                self.order
+                    .iter()
+                    .rev()
+                    .find(|c| !self.wild.contains(c))
+                    .copied()
+            });
+        match target {
+            Some(t) => hand
+                .chars()
+                .map(|c| if self.wild.contains(&c) { t } else { c })
+                .collect(),
+            None => hand.to_string(),
+        }
     }
-    match (a, b) {
-        ('J', _) => Ordering::Less,
-        (_, 'J') => Ordering::Greater,
-        ('A', _) => Ordering::Greater,
-        (_, 'A') => Ordering::Less,
-        ('K', _) => Ordering::Greater,
-        (_, 'K') => Ordering::Less,
-        ('Q', _) => Ordering::Greater,
-        (_, 'Q') => Ordering::Less,
-        ('T', _) => Ordering::Greater,
-        (_, 'T') => Ordering::Less,
-        _ => a.cmp(&b),
+
+    /// The best kind the hand can make.
+    fn kind(&self, hand: &str) -> Kind {
+        let mut cards = HashMap::new();
+        for c in self.substitute(hand).chars() {
+            *cards.entry(c).or_insert(0) += 1;
+        }
+        let mut groups = cards.into_values().collect::<Vec<usize>>();
+        groups.sort_unstable_by(|a, b| b.cmp(a));
+        match groups.as_slice() {
+            [a, ..] if *a >= 5 => Kind::Five,
+            [4, ..] => Kind::Four,
+            [3, b, ..] if *b >= 2 => Kind::Full,
+            [3, ..] => Kind::Three,
+            [2, 2, ..] => Kind::TwoPair,
+            [2, ..] => Kind::Pair,
+            _ => Kind::High,
+        }
+    }
+
+    fn compare(&self, a: &str, b: &str) -> Ordering {
+        self.kind(a).cmp(&self.kind(b)).then_with(|| {
+            a.chars()
+                .map(|c| self.strength(c))
+                .cmp(b.chars().map(|c| self.strength(c)))
+        })
     }
 }
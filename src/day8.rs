@@ -3,141 +3,262 @@ use std::collections::{HashMap, HashSet};
 const DATA: &str = include_str!("day8.txt");
 
 pub fn part1() -> Option<()> {
-    let mut data = DATA.lines();
-    let dirs = data.next()?.chars().collect::<Vec<_>>();
-    let mut intern = HashMap::new();
-    let mut map = HashMap::new();
-    data.next();
-    for line in data {
-        let (start, rest) = line.split_once(" = ")?;
-        let (left, right) = rest.split_once(", ")?;
-        let (l, r) = (left.trim_start_matches("("), right.trim_end_matches(")"));
-        let start_idx = intern.len();
-        let start_e = intern.entry(start).or_insert(start_idx);
-        let ss = *start_e;
-        let left_idx = intern.len();
-        let left_e = intern.entry(l).or_insert(left_idx);
-        let ll = *left_e;
-        let right_idx = intern.len();
-        let right_e = intern.entry(r).or_insert(right_idx);
-        let rr = *right_e;
-        map.insert(ss, (ll, rr));
-    }
+    let network = parse(DATA).map_err(|e| eprintln!("{e}")).ok()?;
+    let result = network.steps(&Pattern::Exact("AAA"), &Pattern::Exact("ZZZ"))?;
+    println!("result = {result}");
+
+    Some(())
+}
 
-    let goal = *intern.get("ZZZ")?;
+pub fn part2() -> Option<()> {
+    let network = parse(DATA).map_err(|e| eprintln!("{e}")).ok()?;
+    let Some(result) = network.steps(&Pattern::Suffix("A"), &Pattern::Suffix("Z")) else {
+        println!("no step count puts every ghost on a goal at once");
+        return None;
+    };
+    println!("result = {result}");
 
-    let mut curr = *intern.get("AAA")?;
+    Some(())
+}
 
-    let mut dir_idx = 0;
-    let mut count = 0;
-    while curr != goal {
-        let dir = dirs[dir_idx];
-        let e = map.get(&curr)?;
-        if dir == 'L' {
-            curr = e.0;
-        } else {
-            curr = e.1;
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Dir {
+    L,
+    R,
+}
+
+impl TryFrom<char> for Dir {
+    type Error = String;
+
+    fn try_from(c: char) -> Result<Self, Self::Error> {
+        match c {
+            'L' => Ok(Dir::L),
+            'R' => Ok(Dir::R),
+            _ => Err(format!("bad direction {c:?}")),
         }
-        dir_idx = (dir_idx + 1) % dirs.len();
-        count += 1;
     }
-    println!("result = {count}");
+}
 
-    Some(())
+/// Which node labels a walk starts or ends on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Pattern<'a> {
+    Exact(&'a str),
+    Suffix(&'a str),
+    /// `*` matches any run of characters and `?` any single one.
+    Glob(&'a str),
 }
 
-fn find_count(
-    start: usize,
-    goals: &HashSet<usize>,
-    map: &HashMap<usize, (usize, usize)>,
-    dirs: &Vec<char>,
-) -> Option<(usize, usize)> {
-    let mut dir_idx = 0;
-    let mut count = 0;
-    let mut curr = start;
-    while !goals.contains(&curr) {
-        let dir = dirs[dir_idx];
-        let e = map.get(&curr)?;
-        if dir == 'L' {
-            curr = e.0;
-        } else {
-            curr = e.1;
+impl Pattern<'_> {
+    fn matches(&self, label: &str) -> bool {
+        match self {
+            Pattern::Exact(p) => label == *p,
+            Pattern::Suffix(p) => label.ends_with(p),
+            Pattern::Glob(p) => glob(p.as_bytes(), label.as_bytes()),
         }
-        dir_idx = (dir_idx + 1) % dirs.len();
-        count += 1;
     }
-    Some((curr, count))
 }
 
-pub fn part2() -> Option<()> {
-    let mut data = DATA.lines();
-    let dirs = data.next()?.chars().collect::<Vec<_>>();
+fn glob(pattern: &[u8], label: &[u8]) -> bool {
+    match (pattern.first(), label.first()) {
+        (None, None) => true,
+        (Some(b'*'), _) => {
+            glob(&pattern[1..], label) || (!label.is_empty() && glob(pattern, &label[1..]))
+        }
+        (Some(b'?'), Some(_)) => glob(&pattern[1..], &label[1..]),
+        (Some(p), Some(l)) if p == l => glob(&pattern[1..], &label[1..]),
+        _ => false,
+    }
+}
+
+/// The instructions and the node graph, with labels interned to indexes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Network<'a> {
+    dirs: Vec<Dir>,
+    labels: Vec<&'a str>,
+    links: Vec<(usize, usize)>,
+}
+
+fn parse(input: &str) -> Result<Network<'_>, String> {
+    let mut data = input.lines();
+    let dirs = data
+        .next()
+        .ok_or("missing directions")?
+        .chars()
+        .map(Dir::try_from)
+        .collect::<Result<Vec<_>, _>>()?;
+    if dirs.is_empty() {
+        return Err("empty directions".to_string());
+    }
+
     let mut intern = HashMap::new();
-    let mut map = HashMap::new();
-    data.next();
-    let mut starts = Vec::new();
-    let mut ends = HashSet::new();
-    for line in data {
-        let (start, rest) = line.split_once(" = ")?;
-        let (left, right) = rest.split_once(", ")?;
+    let mut labels = Vec::new();
+    let mut index = |label| {
+        *intern.entry(label).or_insert_with(|| {
+            labels.push(label);
+            labels.len() - 1
+        })
+    };
+    let mut links = HashMap::new();
+    for line in data.filter(|l| !l.is_empty()) {
+        let (start, rest) = line
+            .split_once(" = ")
+            .ok_or_else(|| format!("bad node {line:?}"))?;
+        let (left, right) = rest
+            .split_once(", ")
+            .ok_or_else(|| format!("bad node {line:?}"))?;
         let (l, r) = (left.trim_start_matches("("), right.trim_end_matches(")"));
-        let start_idx = intern.len();
-        let start_e = intern.entry(start).or_insert(start_idx);
-        let ss = *start_e;
-        let left_idx = intern.len();
-        let left_e = intern.entry(l).or_insert(left_idx);
-        let ll = *left_e;
-        let right_idx = intern.len();
-        let right_e = intern.entry(r).or_insert(right_idx);
-        let rr = *right_e;
-        if start.ends_with("A") {
-            starts.push(ss);
-        }
-        if start.ends_with("Z") {
-            ends.insert(ss);
-        }
-        if l.ends_with("A") {
-            starts.push(ll);
-        }
-        if l.ends_with("Z") {
-            ends.insert(ll);
-        }
-        if r.ends_with("A") {
-            starts.push(rr);
-        }
-        if r.ends_with("Z") {
-            ends.insert(rr);
+        links.insert(index(start), (index(l), index(r)));
+    }
+    let links = (0..labels.len())
+        .map(|i| {
+            links
+                .get(&i)
+                .copied()
+                .ok_or_else(|| format!("node {} has no links", labels[i]))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(Network {
+        dirs,
+        labels,
+        links,
+    })
+}
+
+impl Network<'_> {
+    fn nodes(&self, pattern: &Pattern) -> Vec<usize> {
+        (0..self.labels.len())
+            .filter(|i| pattern.matches(self.labels[*i]))
+            .collect()
+    }
+
+    fn next(&self, node: usize, step: usize) -> usize {
+        match self.dirs[step % self.dirs.len()] {
+            Dir::L => self.links[node].0,
+            Dir::R => self.links[node].1,
         }
-        map.insert(ss, (ll, rr));
     }
 
-    let mut counts = Vec::new();
-    for s in starts.iter() {
-        let (_, c) = find_count(*s, &ends, &map, &dirs)?;
-        counts.push(c);
+    /// The first step at which walks from every node matching `start` are
+    /// all on nodes matching `goal`.
+    fn steps(&self, start: &Pattern, goal: &Pattern) -> Option<usize> {
+        let goals = self.nodes(goal).into_iter().collect::<HashSet<_>>();
+        let cycles = self
+            .nodes(start)
+            .into_iter()
+            .map(|s| self.analyze(s, &goals))
+            .collect::<Vec<_>>();
+        first_common_hit(&cycles)
     }
-    let result = counts.into_iter().reduce(|acc, x| lcm(acc, x))?;
-    println!("result = {result}");
 
-    Some(())
+    /// Walks from `start` until a (node, instruction index) state repeats,
+    /// which must happen since there are finitely many.
+    fn analyze(&self, start: usize, goals: &HashSet<usize>) -> Cycle {
+        let mut seen = HashMap::new();
+        let mut hits = Vec::new();
+        let mut curr = start;
+        let mut step = 0;
+        let loop_start = loop {
+            if let Some(first) = seen.insert((curr, step % self.dirs.len()), step) {
+                break first;
+            }
+            if goals.contains(&curr) {
+                hits.push(step);
+            }
+            curr = self.next(curr, step);
+            step += 1;
+        };
+        let (prefix_hits, cycle_hits) = hits.into_iter().partition(|h| *h < loop_start);
+        Cycle {
+            prefix_hits,
+            start: loop_start,
+            len: step - loop_start,
+            cycle_hits,
+        }
+    }
 }
 
-fn lcm(a: usize, b: usize) -> usize {
-    let g = gcd(a, b);
-    let bb = b / g;
-    a * bb
+/// When a ghost walking from one start sits on a goal node.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Cycle {
+    /// Goal hits before the walk settles into its loop.
+    prefix_hits: Vec<usize>,
+    /// Step at which the loop starts, and how many steps it takes.
+    start: usize,
+    len: usize,
+    /// Goal hits during the first pass of the loop, each recurring every
+    /// `len` steps after.
+    cycle_hits: Vec<usize>,
 }
 
-fn gcd(a: usize, b: usize) -> usize {
-    let (mut x, mut y) = if a > b { (a, b) } else { (b, a) };
-    while x != y {
-        let t = x - y;
-        if t > y {
-            x = t;
+impl Cycle {
+    fn hits(&self, step: usize) -> bool {
+        if step < self.start {
+            self.prefix_hits.contains(&step)
         } else {
-            x = y;
-            y = t;
+            let phase = self.start + (step - self.start) % self.len;
+            self.cycle_hits.contains(&phase)
+        }
+    }
+}
+
+/// The first step at which every ghost is on a goal, if there is one.
+///
+/// Before the slowest ghost enters its loop the steps are simply checked one
+/// by one. After that each ghost is on a goal exactly when the step is
+/// congruent to one of its loop hits modulo its loop length, so every choice
+/// of one hit per ghost is a system of congruences to solve with the CRT.
+fn first_common_hit(cycles: &[Cycle]) -> Option<usize> {
+    let settled = cycles.iter().map(|c| c.start).max()?;
+    if let Some(step) = (0..settled).find(|s| cycles.iter().all(|c| c.hits(*s))) {
+        return Some(step);
+    }
+
+    let mut systems = vec![(0i128, 1i128)];
+    for c in cycles.iter() {
+        let mut next = Vec::new();
+        for sys in systems.iter() {
+            for h in c.cycle_hits.iter() {
+                if let Some(combined) = crt(*sys, (*h as i128, c.len as i128)) {
+                    next.push(combined);
+                }
+            }
         }
+        systems = next;
+    }
+    systems
+        .into_iter()
+        .map(|(r, m)| {
+            let settled = settled as i128;
+            if r >= settled {
+                r
+            } else {
+                r + (settled - r + m - 1) / m * m
+            }
+        })
+        .min()
+        .map(|s| s as usize)
+}
+
+/// Combines `x = a.0 mod a.1` and `x = b.0 mod b.1`, moduli not necessarily
+/// coprime, into a single `x = r mod lcm`, or `None` if they contradict.
+fn crt(a: (i128, i128), b: (i128, i128)) -> Option<(i128, i128)> {
+    let (g, p, _) = ext_gcd(a.1, b.1);
+    let diff = b.0 - a.0;
+    if diff % g != 0 {
+        return None;
+    }
+    let m = a.1 / g * b.1;
+    let k = (diff / g * p).rem_euclid(b.1 / g);
+    Some(((a.0 + a.1 * k).rem_euclid(m), m))
+}
+
+/// Returns `(g, x, y)` with `a * x + b * y = g = gcd(a, b)`.
+fn ext_gcd(a: i128, b: i128) -> (i128, i128, i128) {
+    if b == 0 {
+        (a, 1, 0)
+    } else {
+        let (g, x, y) = ext_gcd(b, a % b);
+        (g, y, x - a / b * y)
     }
-    x
 }
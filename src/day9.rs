@@ -1,84 +1,62 @@
+use crate::sequence::Poly;
+
 const DATA: &str = include_str!("day9.txt");
 
 pub fn part1() -> Option<()> {
-    let mut result = 0;
-    for line in DATA.lines() {
-        let nums = line
-            .split_whitespace()
-            .map(|v| v.parse::<isize>().unwrap())
-            .collect::<Vec<_>>();
-        let n = compute_next(nums);
-        result += n;
-    }
+    let result = sum_extrapolated(DATA, |len| len as i64)?;
     println!("result = {result}");
     Some(())
 }
 
-pub fn part2() {
-    let mut result = 0;
-    for line in DATA.lines() {
-        let nums = line
-            .split_whitespace()
-            .map(|v| v.parse::<isize>().unwrap())
-            .collect::<Vec<_>>();
-        let n = compute_prev(nums);
-        result += n;
-    }
+pub fn part2() -> Option<()> {
+    let result = sum_extrapolated(DATA, |_| -1)?;
     println!("result = {result}");
+    Some(())
 }
 
-fn compute_prev(nums: Vec<isize>) -> isize {
-    let mut firsts = Vec::new();
-    let mut curr = nums;
-    let mut n = Vec::new();
-    let mut done = false;
-    while !done {
-        firsts.push(curr[0]);
-        done = true;
-        for v in curr.windows(2) {
-            let d = v[1] - v[0];
-            if d != 0 {
-                done = false;
-            }
-            n.push(d);
-        }
-        curr = n;
-        n = Vec::new();
-    }
-    let mut v = 0;
-    for k in 0..firsts.len() {
-        v = firsts[firsts.len() - k - 1] - v;
+/// Fits every line and sums the values at `index(line length)`.
+fn sum_extrapolated(input: &str, index: impl Fn(usize) -> i64) -> Option<i128> {
+    let mut result = 0i128;
+    for line in input.lines() {
+        let nums = line
+            .split_whitespace()
+            .map(|v| v.parse::<i64>().ok())
+            .collect::<Option<Vec<_>>>()?;
+        let poly = Poly::fit(&nums).map_err(|e| eprintln!("{e}")).ok()?;
+        let value = poly
+            .at(index(nums.len()))
+            .map_err(|e| eprintln!("{e}"))
+            .ok()?;
+        let Some(sum) = result.checked_add(value) else {
+            eprintln!("sum of extrapolated values overflows i128");
+            return None;
+        };
+        result = sum;
     }
-    v
+    Some(result)
 }
 
-fn compute_next(nums: Vec<isize>) -> isize {
-    let mut firsts = Vec::new();
-    let mut curr = nums;
-    let mut n = Vec::new();
-    let mut done = false;
-    while !done {
-        firsts.push(curr[0]);
-        done = true;
-        for v in curr.windows(2) {
-            let d = v[1] - v[0];
-            if d != 0 {
-                done = false;
-            }
-            n.push(d);
+/// Prints the polynomial fitted to one line and its values over `from..=to`.
+pub fn describe(line: &str, from: i64, to: i64) -> Option<()> {
+    let nums = line
+        .split_whitespace()
+        .map(|v| v.parse::<i64>().ok())
+        .collect::<Option<Vec<_>>>()?;
+    let poly = Poly::fit(&nums).map_err(|e| eprintln!("{e}")).ok()?;
+    let terms = poly
+        .coefficients()
+        .map_err(|e| eprintln!("{e}"))
+        .ok()?
+        .iter()
+        .enumerate()
+        .map(|(i, c)| format!("{c} x^{i}"))
+        .collect::<Vec<_>>();
+    println!("degree {:?}: {}", poly.degree(), terms.join(" + "));
+    for x in from..=to {
+        match poly.at(x) {
+            Ok(value) => println!("f({x}) = {value}"),
+            Err(e) => println!("{e}"),
         }
-        curr = n;
-        n = Vec::new();
     }
-    curr.push(0);
-    n.clear();
-    for k in 0..firsts.len() {
-        n.push(firsts[firsts.len() - k - 1]);
-        for j in 0..curr.len() {
-            n.push(n[j] + curr[j]);
-        }
-        curr = n;
-        n = Vec::new();
-    }
-    curr[curr.len() - 1]
+    Some(())
 }
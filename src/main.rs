@@ -25,7 +25,43 @@ mod day6;
 mod day7;
 mod day8;
 mod day9;
+mod parallel;
+mod sequence;
 
 fn main() {
+    let mut args = std::env::args().skip(1);
+    let mut render = None;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--render" => {
+                render = Some(args.next().expect("--render takes a path, or - for stdout"));
+            }
+            "--threads" => {
+                let n = args
+                    .next()
+                    .and_then(|n| n.parse().ok())
+                    .expect("--threads takes a number");
+                parallel::set_threads(n);
+            }
+            "--unfold" => {
+                let n = args
+                    .next()
+                    .and_then(|n| n.parse().ok())
+                    .expect("--unfold takes a number");
+                if let Err(e) = day12::set_unfold(n) {
+                    eprintln!("{e}");
+                    std::process::exit(1);
+                }
+            }
+            _ => panic!("unknown argument: {arg}"),
+        }
+    }
+    if let Some(path) = render {
+        let path = (path != "-").then(|| std::path::PathBuf::from(path));
+        if day10::render(path.as_deref()).is_none() {
+            std::process::exit(1);
+        }
+        return;
+    }
     day25::part1();
 }
@@ -0,0 +1,63 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+
+/// Number of worker threads to use, 0 meaning "whatever the machine has".
+static THREADS: AtomicUsize = AtomicUsize::new(0);
+
+pub fn set_threads(n: usize) {
+    THREADS.store(n, Ordering::Relaxed);
+}
+
+pub fn threads() -> usize {
+    match THREADS.load(Ordering::Relaxed) {
+        0 => thread::available_parallelism().map_or(1, |n| n.get()),
+        n => n,
+    }
+}
+
+/// Applies `f` to every item on a pool of scoped threads and returns the
+/// results in input order. Workers pull the next unclaimed index from a
+/// shared counter, so a few slow items don't leave the other threads idle.
+pub fn map<T, R, F>(items: &[T], f: F) -> Vec<R>
+where
+    T: Sync,
+    R: Send,
+    F: Fn(&T) -> R + Sync,
+{
+    let num_threads = threads().min(items.len()).max(1);
+    let next = AtomicUsize::new(0);
+    let mut indexed = thread::scope(|s| {
+        let workers = (0..num_threads)
+            .map(|_| {
+                s.spawn(|| {
+                    let mut out = Vec::new();
+                    loop {
+                        let i = next.fetch_add(1, Ordering::Relaxed);
+                        if i >= items.len() {
+                            break;
+                        }
+                        out.push((i, f(&items[i])));
+                    }
+                    out
+                })
+            })
+            .collect::<Vec<_>>();
+        workers
+            .into_iter()
+            .flat_map(|w| w.join().unwrap())
+            .collect::<Vec<_>>()
+    });
+    indexed.sort_unstable_by_key(|(i, _)| *i);
+    indexed.into_iter().map(|(_, r)| r).collect()
+}
+
+/// Like `map` but folds the results with `reduce`, starting from `init`.
+pub fn map_reduce<T, R, F, G>(items: &[T], f: F, init: R, reduce: G) -> R
+where
+    T: Sync,
+    R: Send,
+    F: Fn(&T) -> R + Sync,
+    G: Fn(R, R) -> R,
+{
+    map(items, f).into_iter().fold(init, reduce)
+}
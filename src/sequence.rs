@@ -0,0 +1,156 @@
+use std::fmt;
+
+/// The lowest-degree integer-valued polynomial through a run of samples
+/// `f(0), f(1), ...`, kept in Newton form:
+/// `f(x) = sum_k diffs[k] * binomial(x, k)` with `diffs[k]` the first entry
+/// of the `k`-th row of forward differences.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Poly {
+    diffs: Vec<i128>,
+}
+
+impl Poly {
+    /// Builds the difference table until a row is all zeros. If the samples
+    /// run out first then every polynomial of lower degree than the number of
+    /// samples misses at least one of them, so the extrapolation would be
+    /// arbitrary and this is an error instead.
+    pub fn fit(values: &[i64]) -> Result<Self, String> {
+        let mut row = values.iter().map(|v| *v as i128).collect::<Vec<_>>();
+        let mut diffs = Vec::new();
+        while !row.iter().all(|v| *v == 0) {
+            if row.len() == 1 {
+                return Err(format!(
+                    "no polynomial of degree < {} fits {values:?}",
+                    values.len() - 1
+                ));
+            }
+            diffs.push(row[0]);
+            row = row
+                .windows(2)
+                .map(|w| w[1].checked_sub(w[0]))
+                .collect::<Option<_>>()
+                .ok_or_else(|| format!("differences of {values:?} overflow i128"))?;
+        }
+        if row.is_empty() {
+            return Err("no values to fit".to_string());
+        }
+        Ok(Poly { diffs })
+    }
+
+    pub fn degree(&self) -> Option<usize> {
+        self.diffs.len().checked_sub(1)
+    }
+
+    /// The value at any index, including before the first sample, or an
+    /// error if it doesn't fit in an `i128`. Every `binomial(x, k)` is an
+    /// integer and follows from the last by an exact division, so this never
+    /// leaves the integers.
+    pub fn at(&self, x: i64) -> Result<i128, String> {
+        let overflow = || format!("f({x}) overflows i128");
+        let x = x as i128;
+        let mut binomial = 1i128;
+        let mut total = 0i128;
+        for (k, d) in self.diffs.iter().enumerate() {
+            if k > 0 {
+                // Dividing out the common factor first keeps the product as
+                // small as the result.
+                let k = k as i128;
+                let g = gcd(binomial.abs(), k);
+                binomial = (binomial / g)
+                    .checked_mul((x - k + 1) / (k / g))
+                    .ok_or_else(overflow)?;
+            }
+            total = d
+                .checked_mul(binomial)
+                .and_then(|t| total.checked_add(t))
+                .ok_or_else(overflow)?;
+        }
+        Ok(total)
+    }
+
+    /// Coefficients of `1, x, x^2, ...`, which unlike the values can be
+    /// fractions. Errors if any step of the expansion overflows `i128`.
+    pub fn coefficients(&self) -> Result<Vec<Ratio>, String> {
+        let overflow = || format!("coefficients of degree {:?} overflow i128", self.degree());
+        let mut coeffs = vec![Ratio::from(0); self.diffs.len()];
+        // falling[i] is the coefficient of x^i in x (x - 1) ... (x - k + 1)
+        let mut falling = vec![1i128];
+        let mut factorial = 1i128;
+        for (k, d) in self.diffs.iter().enumerate() {
+            if k > 0 {
+                let k = k as i128;
+                factorial = factorial.checked_mul(k).ok_or_else(overflow)?;
+                let mut next = vec![0i128; falling.len() + 1];
+                for (i, c) in falling.iter().enumerate() {
+                    next[i + 1] = next[i + 1].checked_add(*c).ok_or_else(overflow)?;
+                    next[i] = c
+                        .checked_mul(k - 1)
+                        .and_then(|t| next[i].checked_sub(t))
+                        .ok_or_else(overflow)?;
+                }
+                falling = next;
+            }
+            for (i, c) in falling.iter().enumerate() {
+                let term = d.checked_mul(*c).ok_or_else(overflow)?;
+                coeffs[i] = coeffs[i]
+                    .checked_add(Ratio::new(term, factorial))
+                    .ok_or_else(overflow)?;
+            }
+        }
+        Ok(coeffs)
+    }
+}
+
+/// An exact fraction in lowest terms with a positive denominator.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Ratio {
+    num: i128,
+    den: i128,
+}
+
+impl Ratio {
+    pub fn new(num: i128, den: i128) -> Self {
+        assert!(den != 0, "zero denominator");
+        let g = gcd(num.abs(), den.abs()).max(1) * den.signum();
+        Ratio {
+            num: num / g,
+            den: den / g,
+        }
+    }
+}
+
+impl From<i128> for Ratio {
+    fn from(n: i128) -> Self {
+        Ratio { num: n, den: 1 }
+    }
+}
+
+impl Ratio {
+    /// The sum, or None if it doesn't fit in `i128`s.
+    pub fn checked_add(self, other: Ratio) -> Option<Ratio> {
+        let g = gcd(self.den, other.den);
+        let num = self
+            .num
+            .checked_mul(other.den / g)?
+            .checked_add(other.num.checked_mul(self.den / g)?)?;
+        Some(Ratio::new(num, (self.den / g).checked_mul(other.den)?))
+    }
+}
+
+impl fmt::Display for Ratio {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.den == 1 {
+            write!(f, "{}", self.num)
+        } else {
+            write!(f, "{}/{}", self.num, self.den)
+        }
+    }
+}
+
+fn gcd(a: i128, b: i128) -> i128 {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}